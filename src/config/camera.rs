@@ -0,0 +1,8 @@
+use serde::{Deserialize, Serialize};
+
+/// Image source of the Game Boy Camera
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct Camera {
+    //Still image or animated GIF. Without an image the camera sees a gray picture
+    pub image_path: Option<String>,
+}
@@ -1,3 +1,4 @@
+use rust_boy::lib::gpu::compatibility_palette::ManualPalette;
 use serde::{Deserialize, Serialize};
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -18,3 +19,75 @@ impl Default for ColorPalette {
         }
     }
 }
+
+/// Palette of a DMG game. Selected per game title
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum GamePalette {
+    //The single color palette above
+    Custom,
+    //Picked from the title checksum like the CGB boot rom
+    Automatic,
+    Up,
+    UpA,
+    UpB,
+    Left,
+    LeftA,
+    LeftB,
+    Down,
+    DownA,
+    DownB,
+    Right,
+    RightA,
+    RightB,
+}
+
+impl Default for GamePalette {
+    fn default() -> Self {
+        GamePalette::Custom
+    }
+}
+
+impl GamePalette {
+    pub fn from_manual_palette(manual_palette: ManualPalette) -> Self {
+        match manual_palette {
+            ManualPalette::Up => GamePalette::Up,
+            ManualPalette::UpA => GamePalette::UpA,
+            ManualPalette::UpB => GamePalette::UpB,
+            ManualPalette::Left => GamePalette::Left,
+            ManualPalette::LeftA => GamePalette::LeftA,
+            ManualPalette::LeftB => GamePalette::LeftB,
+            ManualPalette::Down => GamePalette::Down,
+            ManualPalette::DownA => GamePalette::DownA,
+            ManualPalette::DownB => GamePalette::DownB,
+            ManualPalette::Right => GamePalette::Right,
+            ManualPalette::RightA => GamePalette::RightA,
+            ManualPalette::RightB => GamePalette::RightB,
+        }
+    }
+
+    pub fn get_manual_palette(&self) -> Option<ManualPalette> {
+        match self {
+            GamePalette::Custom | GamePalette::Automatic => None,
+            GamePalette::Up => Some(ManualPalette::Up),
+            GamePalette::UpA => Some(ManualPalette::UpA),
+            GamePalette::UpB => Some(ManualPalette::UpB),
+            GamePalette::Left => Some(ManualPalette::Left),
+            GamePalette::LeftA => Some(ManualPalette::LeftA),
+            GamePalette::LeftB => Some(ManualPalette::LeftB),
+            GamePalette::Down => Some(ManualPalette::Down),
+            GamePalette::DownA => Some(ManualPalette::DownA),
+            GamePalette::DownB => Some(ManualPalette::DownB),
+            GamePalette::Right => Some(ManualPalette::Right),
+            GamePalette::RightA => Some(ManualPalette::RightA),
+            GamePalette::RightB => Some(ManualPalette::RightB),
+        }
+    }
+
+    pub fn get_name(&self) -> &'static str {
+        match self {
+            GamePalette::Custom => "Custom",
+            GamePalette::Automatic => "Automatic",
+            _ => self.get_manual_palette().unwrap().name(),
+        }
+    }
+}
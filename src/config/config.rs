@@ -1,7 +1,13 @@
 use crate::config::audio::Audio;
-use crate::config::color_palette::ColorPalette;
+use crate::config::camera::Camera;
+use crate::config::color_palette::{ColorPalette, GamePalette};
 use crate::config::controls::Controls;
+use crate::config::known_rom::KnownMapper;
+use crate::config::savegame::Savegame;
+use crate::config::sgb::Sgb;
+use crate::config::tilt::Tilt;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 
 #[derive(Serialize, Deserialize, Debug)]
 pub struct Config {
@@ -10,6 +16,23 @@ pub struct Config {
     pub color_palette: ColorPalette,
     #[serde(default = "Audio::default")]
     pub audio: Audio,
+    #[serde(default = "Sgb::default")]
+    pub sgb: Sgb,
+    #[serde(default = "Tilt::default")]
+    pub tilt: Tilt,
+    #[serde(default = "Camera::default")]
+    pub camera: Camera,
+    #[serde(default = "Savegame::default")]
+    pub savegame: Savegame,
+    //Start games with header errors like a wrong checksum or missing banks
+    #[serde(default)]
+    pub ignore_header_errors: bool,
+    //Palettes of DMG games by title
+    #[serde(default)]
+    pub game_palettes: HashMap<String, GamePalette>,
+    //Mappers of rom dumps by CRC32, checked before detecting unlicensed mappers by their contents
+    #[serde(default)]
+    pub known_roms: HashMap<String, KnownMapper>,
 }
 
 impl Config {
@@ -30,6 +53,13 @@ impl Default for Config {
             controls: Controls::default(),
             color_palette: ColorPalette::default(),
             audio: Audio::default(),
+            sgb: Sgb::default(),
+            tilt: Tilt::default(),
+            camera: Camera::default(),
+            savegame: Savegame::default(),
+            ignore_header_errors: false,
+            game_palettes: HashMap::new(),
+            known_roms: HashMap::new(),
         }
     }
 }
@@ -0,0 +1,36 @@
+use rust_boy::lib::cartridge::detection::{KnownRoms, UnlicensedMapper};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Mapper of a rom dump which is not recognized by its header
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum KnownMapper {
+    Mmm01,
+    WisdomTree,
+    Sachen,
+    M161,
+}
+
+impl KnownMapper {
+    pub fn get_unlicensed_mapper(&self) -> UnlicensedMapper {
+        match self {
+            KnownMapper::Mmm01 => UnlicensedMapper::Mmm01,
+            KnownMapper::WisdomTree => UnlicensedMapper::WisdomTree,
+            KnownMapper::Sachen => UnlicensedMapper::Sachen,
+            KnownMapper::M161 => UnlicensedMapper::M161,
+        }
+    }
+}
+
+/// Converts the mappers stored by CRC32 in hexadecimal like "1A2B3C4D"
+pub fn create_known_roms(mappers: &HashMap<String, KnownMapper>) -> KnownRoms {
+    let mut known_roms = KnownRoms::new();
+    for (checksum, mapper) in mappers {
+        match u32::from_str_radix(checksum.trim_start_matches("0x"), 16) {
+            Ok(crc32) => known_roms.insert(crc32, mapper.get_unlicensed_mapper()),
+            Err(_) => println!("Invalid CRC32 of known rom: {}", checksum),
+        }
+    }
+
+    known_roms
+}
@@ -1,5 +1,10 @@
 pub mod audio;
+pub mod camera;
 pub mod color_palette;
 pub mod config;
 pub mod config_storage;
 pub mod controls;
+pub mod known_rom;
+pub mod savegame;
+pub mod sgb;
+pub mod tilt;
@@ -0,0 +1,22 @@
+use serde::{Deserialize, Serialize};
+
+/// Where and how often savegames are written
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Savegame {
+    //Directory for all savegames, relative paths start at the working directory
+    pub directory: String,
+    //Seconds between writes of changed saves, 0 only saves when the game is closed
+    pub autosave_interval_seconds: u64,
+    //Number of previous savegames kept as .sav.bak, .sav.bak.1, ...
+    pub backups: usize,
+}
+
+impl Default for Savegame {
+    fn default() -> Self {
+        Savegame {
+            directory: "saves".to_string(),
+            autosave_interval_seconds: 5,
+            backups: 3,
+        }
+    }
+}
@@ -0,0 +1,12 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Sgb {
+    pub enabled: bool,
+}
+
+impl Default for Sgb {
+    fn default() -> Self {
+        Sgb { enabled: true }
+    }
+}
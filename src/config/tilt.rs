@@ -0,0 +1,25 @@
+use serde::{Deserialize, Serialize};
+use winit::event::VirtualKeyCode;
+
+/// Controls for the accelerometer of MBC7 cartridges
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Tilt {
+    //Tilt by the mouse position relative to the window center instead of the keys
+    pub use_mouse: bool,
+    pub left: VirtualKeyCode,
+    pub right: VirtualKeyCode,
+    pub up: VirtualKeyCode,
+    pub down: VirtualKeyCode,
+}
+
+impl Default for Tilt {
+    fn default() -> Self {
+        Tilt {
+            use_mouse: false,
+            left: VirtualKeyCode::Left,
+            right: VirtualKeyCode::Right,
+            up: VirtualKeyCode::Up,
+            down: VirtualKeyCode::Down,
+        }
+    }
+}
@@ -0,0 +1,67 @@
+use image::codecs::gif::GifDecoder;
+use image::imageops::FilterType;
+use image::{AnimationDecoder, DynamicImage, GrayImage};
+use rust_boy::lib::cartridge::pocket_camera::{SENSOR_HEIGHT, SENSOR_WIDTH};
+use rust_boy::lib::cartridge::CameraSensor;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+
+/// Feeds the Game Boy Camera from an image file. Each capture of an animated GIF shows the next frame
+pub struct ImageCamera {
+    frames: Vec<GrayImage>,
+    current_frame: usize,
+}
+
+impl ImageCamera {
+    pub fn new(image_path: &str) -> Result<Self, String> {
+        let frames = load_frames(image_path)
+            .map_err(|error| format!("Could not load camera image {}: {}", image_path, error))?;
+
+        if frames.is_empty() {
+            return Err(format!("Camera image {} contains no frames", image_path));
+        }
+
+        Ok(ImageCamera {
+            frames,
+            current_frame: 0,
+        })
+    }
+}
+
+fn load_frames(image_path: &str) -> image::ImageResult<Vec<GrayImage>> {
+    let is_gif = Path::new(image_path)
+        .extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("gif"));
+
+    let images = if is_gif {
+        let decoder = GifDecoder::new(BufReader::new(File::open(image_path)?))?;
+        decoder
+            .into_frames()
+            .map(|frame| frame.map(|frame| DynamicImage::ImageRgba8(frame.into_buffer())))
+            .collect::<image::ImageResult<Vec<_>>>()?
+    } else {
+        vec![image::open(image_path)?]
+    };
+
+    Ok(images.iter().map(to_sensor_image).collect())
+}
+
+/// Scales the image to fill the sensor and crops the overlapping part
+fn to_sensor_image(image: &DynamicImage) -> GrayImage {
+    image
+        .resize_to_fill(
+            SENSOR_WIDTH as u32,
+            SENSOR_HEIGHT as u32,
+            FilterType::Triangle,
+        )
+        .to_luma8()
+}
+
+impl CameraSensor for ImageCamera {
+    fn capture(&mut self) -> Vec<u8> {
+        let frame = self.frames[self.current_frame].as_raw().clone();
+        self.current_frame = (self.current_frame + 1) % self.frames.len();
+        frame
+    }
+}
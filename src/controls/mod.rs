@@ -1 +1,4 @@
+pub mod image_camera;
 pub mod keyboard_controller;
+pub mod rumble_state;
+pub mod tilt_controller;
@@ -0,0 +1,20 @@
+use rust_boy::lib::cartridge::Rumble;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+/// Shares the rumble motor state of the cartridge with the window
+pub struct RumbleState {
+    active: Arc<AtomicBool>,
+}
+
+impl RumbleState {
+    pub fn new(active: Arc<AtomicBool>) -> Self {
+        RumbleState { active }
+    }
+}
+
+impl Rumble for RumbleState {
+    fn set_rumble(&self, active: bool) {
+        self.active.store(active, Ordering::Relaxed);
+    }
+}
@@ -0,0 +1,90 @@
+use crate::config::config::Config;
+use crate::config::config_storage::ConfigStorage;
+use rust_boy::lib::cartridge::TiltSensor;
+use std::sync::{Arc, Mutex, RwLock};
+use winit::dpi::{PhysicalPosition, PhysicalSize};
+use winit::event::VirtualKeyCode;
+
+/// Maps keys or the mouse to the tilt of the accelerometer
+pub struct TiltController {
+    tilt: Arc<Mutex<(f32, f32)>>,
+    config: Arc<RwLock<Config>>,
+}
+
+impl TiltController {
+    pub fn new(tilt: Arc<Mutex<(f32, f32)>>, config_storage: &ConfigStorage) -> Self {
+        TiltController {
+            tilt,
+            config: Arc::clone(&config_storage.config),
+        }
+    }
+
+    pub fn push_key(&self, key_code: VirtualKeyCode) {
+        let config = self.config.read().unwrap();
+        if config.tilt.use_mouse {
+            return;
+        }
+
+        let mut tilt = self.tilt.lock().unwrap();
+        if key_code == config.tilt.left {
+            tilt.0 = -1.0;
+        } else if key_code == config.tilt.right {
+            tilt.0 = 1.0;
+        } else if key_code == config.tilt.up {
+            tilt.1 = -1.0;
+        } else if key_code == config.tilt.down {
+            tilt.1 = 1.0;
+        }
+    }
+
+    pub fn release_key(&self, key_code: VirtualKeyCode) {
+        let config = self.config.read().unwrap();
+        if config.tilt.use_mouse {
+            return;
+        }
+
+        //Only reset the axis if the released key is the one currently tilting it
+        let mut tilt = self.tilt.lock().unwrap();
+        if (key_code == config.tilt.left && tilt.0 < 0.0)
+            || (key_code == config.tilt.right && tilt.0 > 0.0)
+        {
+            tilt.0 = 0.0;
+        } else if (key_code == config.tilt.up && tilt.1 < 0.0)
+            || (key_code == config.tilt.down && tilt.1 > 0.0)
+        {
+            tilt.1 = 0.0;
+        }
+    }
+
+    /// The window center is flat, the window edges are fully tilted
+    pub fn move_mouse(&self, position: PhysicalPosition<f64>, window_size: PhysicalSize<u32>) {
+        if !self.config.read().unwrap().tilt.use_mouse
+            || window_size.width == 0
+            || window_size.height == 0
+        {
+            return;
+        }
+
+        let x = position.x / window_size.width as f64 * 2.0 - 1.0;
+        let y = position.y / window_size.height as f64 * 2.0 - 1.0;
+
+        *self.tilt.lock().unwrap() = (x.clamp(-1.0, 1.0) as f32, y.clamp(-1.0, 1.0) as f32);
+    }
+}
+
+/// Passes the tilt of the controller to the cartridge
+pub struct TiltInput {
+    tilt: Arc<Mutex<(f32, f32)>>,
+}
+
+impl TiltInput {
+    pub fn new(tilt: Arc<Mutex<(f32, f32)>>) -> Self {
+        TiltInput { tilt }
+    }
+}
+
+impl TiltSensor for TiltInput {
+    fn get_tilt(&self) -> (f32, f32) {
+        *self.tilt.lock().unwrap()
+    }
+}
@@ -1,44 +1,147 @@
 use crate::audio_output::CpalAudioOutput;
 
+use crate::config::config::Config;
+use crate::config::known_rom::create_known_roms;
+use crate::controls::image_camera::ImageCamera;
+use crate::controls::rumble_state::RumbleState;
+use crate::controls::tilt_controller::TiltInput;
 use crate::graphics::gameboy_screen::GameboyScreen;
+use crate::rom_file::RomFile;
 use crate::savegame::filesystem_ram_dumper::FilesystemRamDumper;
 use crate::EmulationSignal;
 use rust_boy::lib::apu::apu::Apu;
 use rust_boy::lib::cartridge;
+use rust_boy::lib::cartridge::header::CartridgeHeader;
+use rust_boy::lib::cartridge::{CameraSensor, Cartridge, HeaderValidation, Peripherals, RamDumper};
 use rust_boy::lib::cpu::cpu::Cpu;
 use rust_boy::lib::gpu::gpu::Gpu;
 use rust_boy::lib::io::joypad::Joypad;
 use rust_boy::lib::memory::mmu::Mmu;
+use rust_boy::lib::patch;
 
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::mpsc::{channel, Sender};
-use std::sync::{Arc, Mutex};
+use std::sync::{Arc, Mutex, RwLock};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
 use std::{fs, thread};
 
+//Patches next to the ROM with the same name are applied automatically
+const PATCH_EXTENSIONS: [&str; 3] = ["ips", "ups", "bps"];
+
+/// ROM chosen by the user with an optional patch
+pub struct RomSelection {
+    pub rom_path: String,
+    //ROM inside a zip archive, the first one if not set
+    pub archive_entry: Option<String>,
+    pub patch_path: Option<String>,
+}
+
+impl RomSelection {
+    pub fn new(rom_path: String) -> Self {
+        RomSelection {
+            rom_path,
+            archive_entry: None,
+            patch_path: None,
+        }
+    }
+}
+
 pub struct Emulation {
     gameboy_screen: Arc<GameboyScreen>,
     joypad: Arc<Mutex<Joypad>>,
+    config: Arc<RwLock<Config>>,
+    rumble_active: Arc<AtomicBool>,
+    tilt: Arc<Mutex<(f32, f32)>>,
 }
 
 impl Emulation {
-    pub fn new(gameboy_screen: Arc<GameboyScreen>, joypad: Arc<Mutex<Joypad>>) -> Self {
+    pub fn new(
+        gameboy_screen: Arc<GameboyScreen>,
+        joypad: Arc<Mutex<Joypad>>,
+        config: Arc<RwLock<Config>>,
+        rumble_active: Arc<AtomicBool>,
+        tilt: Arc<Mutex<(f32, f32)>>,
+    ) -> Self {
         Emulation {
             gameboy_screen,
             joypad,
+            config,
+            rumble_active,
+            tilt,
         }
     }
 
-    pub fn start(&self, rom_path: &String) -> Result<Sender<EmulationSignal>, String> {
-        let rom = read_rom_from_file(rom_path)?;
-        let ram_dumper = FilesystemRamDumper::new(&rom_path);
-        let mut cartridge = cartridge::new_cartridge(rom, Some(Box::new(ram_dumper)))?;
+    /// The emulation thread writes the savegame before it ends, join it before exiting
+    pub fn start(
+        &self,
+        selection: &RomSelection,
+    ) -> Result<(Sender<EmulationSignal>, JoinHandle<()>), String> {
+        let rom_file = RomFile::read(&selection.rom_path, selection.archive_entry.as_deref())?;
+        let rom_path = &rom_file.path;
+        let mut rom = rom_file.data;
+        let patch_path = selection
+            .patch_path
+            .clone()
+            .or_else(|| find_patch(rom_path));
+        if let Some(patch_path) = &patch_path {
+            rom = apply_patch_file(&rom, patch_path)?;
+            println!("{}: applied patch {}", rom_path, patch_path);
+        }
+        let header = CartridgeHeader::parse(&rom).map_err(|error| error.to_string())?;
+        let header_errors = header.validate(&rom);
+        for error in &header_errors {
+            println!("{}: {}", rom_path, error);
+        }
+        self.gameboy_screen.set_rom_info(header, header_errors);
+        self.gameboy_screen.load_game(&rom);
+        let (ram_dumper, autosave_interval) = {
+            let config = self.config.read().unwrap();
+            let savegame = &config.savegame;
+            (
+                FilesystemRamDumper::new(
+                    rom_path,
+                    patch_path.as_deref(),
+                    &savegame.directory,
+                    savegame.backups,
+                ),
+                Duration::from_secs(savegame.autosave_interval_seconds),
+            )
+        };
+        self.rumble_active.store(false, Ordering::Relaxed);
+        let rumble = RumbleState::new(Arc::clone(&self.rumble_active));
+        let tilt_input = TiltInput::new(Arc::clone(&self.tilt));
+        let camera = self.load_camera();
+        let peripherals = Peripherals {
+            rumble: Some(Box::new(rumble)),
+            tilt_sensor: Some(Box::new(tilt_input)),
+            infrared: None,
+            camera,
+        };
+        let (validation, known_roms) = {
+            let config = self.config.read().unwrap();
+            let validation = if config.ignore_header_errors {
+                HeaderValidation::WarnOnly
+            } else {
+                HeaderValidation::Strict
+            };
+            (validation, create_known_roms(&config.known_roms))
+        };
+        let mut cartridge = cartridge::new_cartridge(rom, peripherals, validation, &known_roms)
+            .map_err(|error| error.to_string())?;
+        if let Err(error) = ram_dumper.load_cartridge(&mut *cartridge) {
+            println!("Could not load savegame: {}", error);
+        }
 
         let (emulation_signal_sender, emulation_signal_receiver) = channel();
         let cloned_sender = emulation_signal_sender.clone();
 
         let screen = Arc::clone(&self.gameboy_screen);
+        let sgb_enabled = self.config.read().unwrap().sgb.enabled;
         let joypad = Arc::clone(&self.joypad);
 
-        thread::Builder::new()
+        let emulation_thread = thread::Builder::new()
             .name("emulation".to_string())
             .spawn(move || {
                 //Cpal needs to be startet from a different thread because of a winit bug on windows
@@ -50,14 +153,20 @@ impl Emulation {
                 let mut apu = Apu::new(&mut audio_output);
                 let mut gpu = Gpu::new(screen);
                 let mut mmu = Mmu::new(&mut *cartridge, &mut gpu, &mut apu);
-                let mut cpu = Cpu::new();
+                if !sgb_enabled {
+                    mmu.disable_sgb();
+                }
+                let mut cpu = Cpu::new(mmu.hardware_mode);
                 let mut emulation = rust_boy::lib::emulation::Emulation::new();
+                let mut last_autosave = Instant::now();
 
                 loop {
                     let signal = emulation_signal_receiver.recv().unwrap();
 
                     if let EmulationSignal::Quit = signal {
-                        mmu.save();
+                        if let Err(error) = ram_dumper.save_cartridge(mmu.cartridge()) {
+                            println!("Could not write savegame: {}", error);
+                        }
                         audio_output.stop();
                         break;
                     }
@@ -65,17 +174,58 @@ impl Emulation {
                     let joypad = joypad.lock().unwrap();
 
                     emulation.cycle(&mut cpu, &mut mmu, &joypad);
+
+                    if !autosave_interval.is_zero() && last_autosave.elapsed() >= autosave_interval
+                    {
+                        last_autosave = Instant::now();
+                        autosave(&ram_dumper, mmu.cartridge_mut());
+                    }
                 }
             })
             .unwrap();
 
-        Ok(cloned_sender)
+        Ok((cloned_sender, emulation_thread))
+    }
+
+    /// A missing or broken image only disables the camera image
+    fn load_camera(&self) -> Option<Box<dyn CameraSensor + Send>> {
+        let config = self.config.read().unwrap();
+        let image_path = config.camera.image_path.as_ref()?;
+
+        match ImageCamera::new(image_path) {
+            Ok(camera) => Some(Box::new(camera)),
+            Err(error) => {
+                eprintln!("{}", error);
+                None
+            }
+        }
     }
 }
 
-fn read_rom_from_file(rom_path: &String) -> Result<Vec<u8>, String> {
-    match fs::read(rom_path) {
-        Ok(rom) => Ok(rom),
-        Err(_) => Err(format!("Could not open file {}", rom_path)),
+/// Only writes the savegame if the cartridge RAM changed since the last write
+fn autosave(ram_dumper: &FilesystemRamDumper, cartridge: &mut dyn Cartridge) {
+    if !cartridge.is_dirty() {
+        return;
     }
+
+    match ram_dumper.save_cartridge(cartridge) {
+        Ok(()) => cartridge.clear_dirty(),
+        Err(error) => println!("Could not write savegame: {}", error),
+    }
+}
+
+/// Patch with the name of the ROM and an IPS, UPS or BPS extension
+fn find_patch(rom_path: &str) -> Option<String> {
+    PATCH_EXTENSIONS
+        .iter()
+        .map(|extension| Path::new(rom_path).with_extension(extension))
+        .find(|path| path.is_file())
+        .map(|path| path.to_string_lossy().into_owned())
+}
+
+fn apply_patch_file(rom: &[u8], patch_path: &str) -> Result<Vec<u8>, String> {
+    let patch_data =
+        fs::read(patch_path).map_err(|_| format!("Could not open patch {}", patch_path))?;
+    patch::apply_patch(rom, &patch_data)
+        .map_err(|error| format!("Could not apply patch {}: {}", patch_path, error))
 }
@@ -1,6 +1,13 @@
+use crate::config::color_palette::GamePalette;
 use crate::config::config::Config;
-use rust_boy::lib::gpu::{Screen, BUFFER_SIZE, SCREEN_HEIGHT, SCREEN_WIDTH};
-use std::sync::atomic::{AtomicU8, Ordering};
+use rust_boy::lib::cartridge;
+use rust_boy::lib::cartridge::header::{CartridgeHeader, HeaderError};
+use rust_boy::lib::gpu::compatibility_palette::{CompatibilityPalette, ManualPalette};
+use rust_boy::lib::gpu::{
+    Screen, BUFFER_SIZE, SCREEN_HEIGHT, SCREEN_WIDTH, SGB_BUFFER_SIZE, SGB_SCREEN_HEIGHT,
+    SGB_SCREEN_WIDTH,
+};
+use std::sync::atomic::{AtomicBool, AtomicU8, Ordering};
 use std::sync::{Arc, Mutex, RwLock};
 
 pub const MENU_BAR_HEIGHT: i32 = 19;
@@ -9,7 +16,13 @@ pub struct GameboyScreen {
     buffer1: Arc<Mutex<[u8; BUFFER_SIZE]>>,
     buffer2: Arc<Mutex<[u8; BUFFER_SIZE]>>,
     current_buffer: Arc<AtomicU8>,
+    sgb_buffer: Mutex<Vec<u8>>,
+    sgb_active: AtomicBool,
     config: Arc<RwLock<Config>>,
+    game_title: Mutex<String>,
+    automatic_palette: Mutex<CompatibilityPalette>,
+    //Header of the running game and the problems found in it
+    rom_info: Mutex<Option<(CartridgeHeader, Vec<HeaderError>)>>,
 }
 
 impl GameboyScreen {
@@ -18,10 +31,33 @@ impl GameboyScreen {
             buffer1: Arc::new(Mutex::new([255; BUFFER_SIZE])),
             buffer2: Arc::new(Mutex::new([255; BUFFER_SIZE])),
             current_buffer: Arc::new(AtomicU8::new(1)),
+            sgb_buffer: Mutex::new(vec![255; SGB_BUFFER_SIZE]),
+            sgb_active: AtomicBool::new(false),
             config,
+            game_title: Mutex::new(String::new()),
+            automatic_palette: Mutex::new(ManualPalette::RightA.palette()),
+            rom_info: Mutex::new(None),
         }
     }
 
+    /// Remembers the running game to pick its palette
+    pub fn load_game(&self, rom: &[u8]) {
+        *self.game_title.lock().unwrap() = cartridge::get_title(rom);
+        *self.automatic_palette.lock().unwrap() = CompatibilityPalette::from_header(rom);
+    }
+
+    pub fn set_rom_info(&self, header: CartridgeHeader, errors: Vec<HeaderError>) {
+        *self.rom_info.lock().unwrap() = Some((header, errors));
+    }
+
+    pub fn get_rom_info(&self) -> Option<(CartridgeHeader, Vec<HeaderError>)> {
+        self.rom_info.lock().unwrap().clone()
+    }
+
+    pub fn get_game_title(&self) -> String {
+        self.game_title.lock().unwrap().clone()
+    }
+
     pub fn draw_to_queue(
         &self,
         queue: &wgpu::Queue,
@@ -36,33 +72,75 @@ impl GameboyScreen {
             self.buffer2.lock().unwrap()
         };
 
-        let mut texture_output = [0; SCREEN_WIDTH * SCREEN_HEIGHT * 4];
+        write_texture(
+            queue,
+            texture,
+            texture_size,
+            &pixel_data,
+            SCREEN_WIDTH,
+            SCREEN_HEIGHT,
+        );
+    }
 
-        for (i, pixel) in texture_output.iter_mut().enumerate() {
-            if i % 4 < 3 {
-                let data_index = i - (i / 4);
-                *pixel = pixel_data[data_index];
-            } else {
-                *pixel = 0;
-            }
-        }
+    /// Draws the game screen with the SGB border
+    pub fn draw_sgb_to_queue(
+        &self,
+        queue: &wgpu::Queue,
+        texture: &wgpu::Texture,
+        texture_size: wgpu::Extent3d,
+    ) {
+        let pixel_data = self.sgb_buffer.lock().unwrap();
 
-        queue.write_texture(
-            wgpu::ImageCopyTexture {
-                texture: &texture,
-                mip_level: 0,
-                origin: wgpu::Origin3d::ZERO,
-                aspect: wgpu::TextureAspect::All,
-            },
-            &texture_output,
-            wgpu::ImageDataLayout {
-                offset: 0,
-                bytes_per_row: std::num::NonZeroU32::new(4 * SCREEN_WIDTH as u32),
-                rows_per_image: std::num::NonZeroU32::new(SCREEN_HEIGHT as u32),
-            },
+        write_texture(
+            queue,
+            texture,
             texture_size,
+            &*pixel_data,
+            SGB_SCREEN_WIDTH,
+            SGB_SCREEN_HEIGHT,
         );
     }
+
+    /// True if the running game is drawn by the SGB
+    pub fn is_sgb_active(&self) -> bool {
+        self.sgb_active.load(Ordering::SeqCst)
+    }
+}
+
+fn write_texture(
+    queue: &wgpu::Queue,
+    texture: &wgpu::Texture,
+    texture_size: wgpu::Extent3d,
+    pixel_data: &[u8],
+    width: usize,
+    height: usize,
+) {
+    let mut texture_output = vec![0; width * height * 4];
+
+    for (i, pixel) in texture_output.iter_mut().enumerate() {
+        if i % 4 < 3 {
+            let data_index = i - (i / 4);
+            *pixel = pixel_data[data_index];
+        } else {
+            *pixel = 0;
+        }
+    }
+
+    queue.write_texture(
+        wgpu::ImageCopyTexture {
+            texture: &texture,
+            mip_level: 0,
+            origin: wgpu::Origin3d::ZERO,
+            aspect: wgpu::TextureAspect::All,
+        },
+        &texture_output,
+        wgpu::ImageDataLayout {
+            offset: 0,
+            bytes_per_row: std::num::NonZeroU32::new(4 * width as u32),
+            rows_per_image: std::num::NonZeroU32::new(height as u32),
+        },
+        texture_size,
+    );
 }
 
 impl Screen for GameboyScreen {
@@ -76,6 +154,15 @@ impl Screen for GameboyScreen {
         };
 
         *buffer = *screen_buffer;
+        self.sgb_active.store(false, Ordering::SeqCst);
+    }
+
+    fn draw_sgb(&self, screen_buffer: &[u8; SGB_BUFFER_SIZE]) {
+        self.sgb_buffer
+            .lock()
+            .unwrap()
+            .copy_from_slice(screen_buffer);
+        self.sgb_active.store(true, Ordering::SeqCst);
     }
 
     fn get_palette(&self) -> [[u8; 3]; 4] {
@@ -87,4 +174,21 @@ impl Screen for GameboyScreen {
             palette.color1,
         ]
     }
+
+    fn get_compatibility_palette(&self) -> Option<CompatibilityPalette> {
+        let config = self.config.read().unwrap();
+        let game_palette = config
+            .game_palettes
+            .get(&*self.game_title.lock().unwrap())
+            .copied()
+            .unwrap_or_default();
+
+        match game_palette {
+            GamePalette::Custom => None,
+            GamePalette::Automatic => Some(*self.automatic_palette.lock().unwrap()),
+            _ => game_palette
+                .get_manual_palette()
+                .map(|manual_palette| manual_palette.palette()),
+        }
+    }
 }
@@ -1,29 +1,46 @@
 use crate::config::config::Config;
+use crate::emulation::RomSelection;
+use crate::graphics::gameboy_screen::GameboyScreen;
 use crate::graphics::gui::controls_window::ControlsWindow;
 use crate::graphics::gui::main_menu::MainMenu;
 use crate::graphics::gui::palette_window::PaletteWindow;
+use crate::graphics::gui::rom_info_window::RomInfoWindow;
+use crate::graphics::gui::rom_picker_window::RomPickerWindow;
 use crate::graphics::gui::State;
 use egui::{CtxRef, TextureId};
 use epi::Frame;
 use std::sync::mpsc::Sender;
-use std::sync::{Arc, RwLock};
+use std::sync::{Arc, Mutex, RwLock};
 use winit::event::KeyboardInput;
 
 pub struct EmulatorApp {
     main_menu: MainMenu,
     controls_window: ControlsWindow,
     palette_window: PaletteWindow,
+    rom_info_window: RomInfoWindow,
+    rom_picker_window: RomPickerWindow,
     state: State,
     keyboard_input: Option<KeyboardInput>,
     tex: Option<TextureId>,
 }
 
 impl EmulatorApp {
-    pub fn new(rom_filename_sender: Sender<Option<String>>, config: &Arc<RwLock<Config>>) -> Self {
+    pub fn new(
+        rom_filename_sender: Sender<Option<RomSelection>>,
+        config: &Arc<RwLock<Config>>,
+        gameboy_screen: Arc<GameboyScreen>,
+    ) -> Self {
+        let archive_choice = Arc::new(Mutex::new(None));
         EmulatorApp {
-            main_menu: MainMenu::new(rom_filename_sender),
+            main_menu: MainMenu::new(
+                rom_filename_sender.clone(),
+                config.clone(),
+                Arc::clone(&archive_choice),
+            ),
             controls_window: ControlsWindow::new(config.clone()),
-            palette_window: PaletteWindow::new(config.clone()),
+            palette_window: PaletteWindow::new(config.clone(), Arc::clone(&gameboy_screen)),
+            rom_info_window: RomInfoWindow::new(config.clone(), gameboy_screen),
+            rom_picker_window: RomPickerWindow::new(rom_filename_sender, archive_choice),
             state: State::new(),
             keyboard_input: None,
             tex: None,
@@ -48,6 +65,8 @@ impl epi::App for EmulatorApp {
         self.controls_window
             .update(ctx, &mut self.state, self.keyboard_input);
         self.palette_window.update(ctx, &mut self.state);
+        self.rom_info_window.update(ctx, &mut self.state);
+        self.rom_picker_window.update(ctx);
 
         egui::CentralPanel::default().show(ctx, |ui| {
             match self.tex {
@@ -1,15 +1,31 @@
+use crate::config::config::Config;
+use crate::emulation::RomSelection;
+use crate::graphics::gui::rom_picker_window::ArchiveChoice;
 use crate::graphics::gui::State;
+use crate::rom_file;
 use std::sync::mpsc::Sender;
+use std::sync::{Arc, Mutex, RwLock};
 use std::thread;
 
+//Zip and gzip archives are unpacked when the game starts
+const ROM_FILTER: [&str; 4] = ["*.gb", "*.gbc", "*.zip", "*.gz"];
+
 pub struct MainMenu {
-    rom_filename_sender: Sender<Option<String>>,
+    rom_filename_sender: Sender<Option<RomSelection>>,
+    config: Arc<RwLock<Config>>,
+    archive_choice: Arc<Mutex<Option<ArchiveChoice>>>,
 }
 
 impl MainMenu {
-    pub fn new(rom_filename_sender: Sender<Option<String>>) -> Self {
+    pub fn new(
+        rom_filename_sender: Sender<Option<RomSelection>>,
+        config: Arc<RwLock<Config>>,
+        archive_choice: Arc<Mutex<Option<ArchiveChoice>>>,
+    ) -> Self {
         MainMenu {
             rom_filename_sender,
+            config,
+            archive_choice,
         }
     }
 
@@ -18,17 +34,52 @@ impl MainMenu {
             ui.menu_button("File", |ui| {
                 if ui.button("Open").clicked() {
                     let filename_sender = self.rom_filename_sender.clone();
+                    let archive_choice = Arc::clone(&self.archive_choice);
                     //Thread is required otherwise this will crash on Windows TODO: Check if this is still true
                     thread::spawn(move || {
                         let filename = tinyfiledialogs::open_file_dialog(
                             "Open",
                             "",
-                            Some((&["*.gb", "*.gbc"], "Gameboy ROM")),
+                            Some((&ROM_FILTER, "Gameboy ROM")),
+                        );
+                        if let Some(filename) = filename {
+                            open_rom(
+                                RomSelection::new(filename),
+                                &filename_sender,
+                                &archive_choice,
+                            );
+                        }
+                    });
+                    ui.close_menu();
+                }
+
+                //Without a patch the ROM is opened like with Open
+                if ui.button("Open with Patch").clicked() {
+                    let filename_sender = self.rom_filename_sender.clone();
+                    let archive_choice = Arc::clone(&self.archive_choice);
+                    thread::spawn(move || {
+                        let filename = tinyfiledialogs::open_file_dialog(
+                            "Open",
+                            "",
+                            Some((&ROM_FILTER, "Gameboy ROM")),
                         );
-                        filename_sender.send(filename).unwrap();
+                        if let Some(filename) = filename {
+                            let mut selection = RomSelection::new(filename);
+                            selection.patch_path = tinyfiledialogs::open_file_dialog(
+                                "Patch",
+                                "",
+                                Some((&["*.ips", "*.ups", "*.bps"], "Patch")),
+                            );
+                            open_rom(selection, &filename_sender, &archive_choice);
+                        }
                     });
                     ui.close_menu();
                 }
+
+                if ui.button("ROM Info").clicked() {
+                    state.rom_info_window_shown = true;
+                    ui.close_menu();
+                }
             });
 
             ui.menu_button("Options", |ui| {
@@ -42,6 +93,38 @@ impl MainMenu {
                     ui.close_menu();
                 }
 
+                //Used by the Game Boy Camera the next time a game is started
+                if ui.button("Camera Image").clicked() {
+                    let config = Arc::clone(&self.config);
+                    thread::spawn(move || {
+                        let filename = tinyfiledialogs::open_file_dialog(
+                            "Camera Image",
+                            "",
+                            Some((&["*.png", "*.jpg", "*.jpeg", "*.gif", "*.bmp"], "Image")),
+                        );
+                        if filename.is_some() {
+                            config.write().unwrap().camera.image_path = filename;
+                        }
+                    });
+                    ui.close_menu();
+                }
+
+                //Used by the next game that is started
+                if ui.button("Save Directory").clicked() {
+                    let config = Arc::clone(&self.config);
+                    thread::spawn(move || {
+                        let current_directory = config.read().unwrap().savegame.directory.clone();
+                        let directory = tinyfiledialogs::select_folder_dialog(
+                            "Save Directory",
+                            &current_directory,
+                        );
+                        if let Some(directory) = directory {
+                            config.write().unwrap().savegame.directory = directory;
+                        }
+                    });
+                    ui.close_menu();
+                }
+
                 if ui.button("Toggle Audio").clicked() {
                     state.audio_enabled = !state.audio_enabled;
                     ui.close_menu();
@@ -50,3 +133,18 @@ impl MainMenu {
         });
     }
 }
+
+/// Zip archives with several ROMs are opened after choosing one in the ROM picker. Errors
+/// are shown when the game is started
+fn open_rom(
+    selection: RomSelection,
+    rom_filename_sender: &Sender<Option<RomSelection>>,
+    archive_choice: &Mutex<Option<ArchiveChoice>>,
+) {
+    match rom_file::archive_entries(&selection.rom_path) {
+        Ok(entries) if entries.len() > 1 => {
+            *archive_choice.lock().unwrap() = Some(ArchiveChoice { selection, entries });
+        }
+        _ => rom_filename_sender.send(Some(selection)).unwrap(),
+    }
+}
@@ -2,10 +2,13 @@ mod controls_window;
 pub mod emulator_app;
 mod main_menu;
 pub mod palette_window;
+mod rom_info_window;
+mod rom_picker_window;
 
 pub struct State {
     controls_window_shown: bool,
     palette_window_shown: bool,
+    rom_info_window_shown: bool,
     audio_enabled: bool,
 }
 
@@ -14,6 +17,7 @@ impl State {
         State {
             controls_window_shown: false,
             palette_window_shown: false,
+            rom_info_window_shown: false,
             audio_enabled: true,
         }
     }
@@ -1,10 +1,13 @@
-use crate::config::color_palette::ColorPalette;
+use crate::config::color_palette::{ColorPalette, GamePalette};
 use crate::config::config::Config;
+use crate::graphics::gameboy_screen::GameboyScreen;
 use crate::graphics::gui::State;
+use rust_boy::lib::gpu::compatibility_palette::MANUAL_PALETTES;
 use std::sync::{Arc, RwLock};
 
 pub struct PaletteWindow {
     config: Arc<RwLock<Config>>,
+    gameboy_screen: Arc<GameboyScreen>,
     color1: [u8; 3],
     color2: [u8; 3],
     color3: [u8; 3],
@@ -12,11 +15,12 @@ pub struct PaletteWindow {
 }
 
 impl PaletteWindow {
-    pub fn new(config: Arc<RwLock<Config>>) -> Self {
+    pub fn new(config: Arc<RwLock<Config>>, gameboy_screen: Arc<GameboyScreen>) -> Self {
         let cloned_config = Arc::clone(&config);
         let palette = &cloned_config.read().unwrap().color_palette;
         PaletteWindow {
             config,
+            gameboy_screen,
             color1: palette.color1,
             color2: palette.color2,
             color3: palette.color3,
@@ -28,6 +32,16 @@ impl PaletteWindow {
         egui::Window::new("Palette")
             .open(&mut state.palette_window_shown)
             .show(ctx, |ui| {
+                let mut sgb_enabled = self.config.read().unwrap().sgb.enabled;
+                if ui
+                    .checkbox(&mut sgb_enabled, "Super Game Boy (applies on next start)")
+                    .changed()
+                {
+                    self.config.write().unwrap().sgb.enabled = sgb_enabled;
+                }
+
+                self.update_game_palette(ui);
+
                 ui.columns(2, |ui| {
                     let col1 = ui.get_mut(0).unwrap();
 
@@ -108,6 +122,47 @@ impl PaletteWindow {
             });
     }
 
+    /// DMG games can use the custom palette or the colors of the CGB instead
+    fn update_game_palette(&mut self, ui: &mut egui::Ui) {
+        let game_title = self.gameboy_screen.get_game_title();
+        if game_title.is_empty() {
+            return;
+        }
+
+        let selected_palette = self
+            .config
+            .read()
+            .unwrap()
+            .game_palettes
+            .get(&game_title)
+            .copied()
+            .unwrap_or_default();
+        let mut game_palette = selected_palette;
+
+        let mut game_palettes = vec![GamePalette::Custom, GamePalette::Automatic];
+        game_palettes.extend(
+            MANUAL_PALETTES
+                .iter()
+                .map(|&p| GamePalette::from_manual_palette(p)),
+        );
+
+        egui::ComboBox::from_label(game_title.as_str())
+            .selected_text(game_palette.get_name())
+            .show_ui(ui, |ui| {
+                for palette in game_palettes {
+                    ui.selectable_value(&mut game_palette, palette, palette.get_name());
+                }
+            });
+
+        if game_palette != selected_palette {
+            self.config
+                .write()
+                .unwrap()
+                .game_palettes
+                .insert(game_title, game_palette);
+        }
+    }
+
     fn load_color_preset(&mut self, palette: [[u8; 3]; 4]) {
         let mut config = self.config.write().unwrap();
         self.color1 = palette[0];
@@ -0,0 +1,103 @@
+use crate::config::config::Config;
+use crate::graphics::gameboy_screen::GameboyScreen;
+use crate::graphics::gui::State;
+use rust_boy::lib::cartridge::header::CartridgeHeader;
+use std::sync::{Arc, RwLock};
+
+pub struct RomInfoWindow {
+    config: Arc<RwLock<Config>>,
+    gameboy_screen: Arc<GameboyScreen>,
+}
+
+impl RomInfoWindow {
+    pub fn new(config: Arc<RwLock<Config>>, gameboy_screen: Arc<GameboyScreen>) -> Self {
+        RomInfoWindow {
+            config,
+            gameboy_screen,
+        }
+    }
+
+    pub fn update(&mut self, ctx: &egui::CtxRef, state: &mut State) {
+        egui::Window::new("ROM Info")
+            .open(&mut state.rom_info_window_shown)
+            .show(ctx, |ui| {
+                match self.gameboy_screen.get_rom_info() {
+                    Some((header, errors)) => {
+                        egui::Grid::new("rom_info_grid")
+                            .striped(true)
+                            .show(ui, |ui| {
+                                for (name, value) in header_rows(&header) {
+                                    ui.label(name);
+                                    ui.label(value);
+                                    ui.end_row();
+                                }
+                            });
+
+                        ui.separator();
+                        if errors.is_empty() {
+                            ui.label("No header errors");
+                        }
+                        for error in errors {
+                            ui.colored_label(egui::Color32::RED, error.to_string());
+                        }
+                    }
+                    None => {
+                        ui.label("No game loaded");
+                    }
+                }
+
+                ui.separator();
+                let mut ignore_header_errors = self.config.read().unwrap().ignore_header_errors;
+                if ui
+                    .checkbox(
+                        &mut ignore_header_errors,
+                        "Start games with header errors (applies on next start)",
+                    )
+                    .changed()
+                {
+                    self.config.write().unwrap().ignore_header_errors = ignore_header_errors;
+                }
+            });
+    }
+}
+
+fn header_rows(header: &CartridgeHeader) -> Vec<(&'static str, String)> {
+    let size = |size: Option<usize>| {
+        size.map_or("None".to_string(), |size| format!("{} KiB", size / 1024))
+    };
+
+    vec![
+        ("Title", header.title.clone()),
+        (
+            "Manufacturer",
+            header.manufacturer_code.clone().unwrap_or_default(),
+        ),
+        ("Licensee", header.licensee_code.clone()),
+        ("Cartridge type", format!("0x{:02X}", header.cartridge_type)),
+        ("ROM size", size(header.rom_size)),
+        ("RAM size", size(header.ram_size)),
+        ("Game Boy Color", yes_no(header.supports_cgb())),
+        ("Super Game Boy", yes_no(header.supports_sgb())),
+        (
+            "Destination",
+            match header.destination_code {
+                0x00 => "Japan".to_string(),
+                _ => "Overseas".to_string(),
+            },
+        ),
+        ("Version", header.version.to_string()),
+        (
+            "Header checksum",
+            format!("0x{:02X}", header.header_checksum),
+        ),
+        (
+            "Global checksum",
+            format!("0x{:04X}", header.global_checksum),
+        ),
+        ("Nintendo logo", yes_no(header.logo_valid)),
+    ]
+}
+
+fn yes_no(value: bool) -> String {
+    if value { "Yes" } else { "No" }.to_string()
+}
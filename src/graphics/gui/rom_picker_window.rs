@@ -0,0 +1,54 @@
+use crate::emulation::RomSelection;
+use std::sync::mpsc::Sender;
+use std::sync::{Arc, Mutex};
+
+/// Zip archive with several ROMs waiting for the user to choose one
+pub struct ArchiveChoice {
+    pub selection: RomSelection,
+    pub entries: Vec<String>,
+}
+
+pub struct RomPickerWindow {
+    rom_filename_sender: Sender<Option<RomSelection>>,
+    archive_choice: Arc<Mutex<Option<ArchiveChoice>>>,
+}
+
+impl RomPickerWindow {
+    pub fn new(
+        rom_filename_sender: Sender<Option<RomSelection>>,
+        archive_choice: Arc<Mutex<Option<ArchiveChoice>>>,
+    ) -> Self {
+        RomPickerWindow {
+            rom_filename_sender,
+            archive_choice,
+        }
+    }
+
+    pub fn update(&mut self, ctx: &egui::CtxRef) {
+        let mut archive_choice = self.archive_choice.lock().unwrap();
+        let mut open = archive_choice.is_some();
+        let mut chosen_entry = None;
+
+        if let Some(choice) = archive_choice.as_ref() {
+            egui::Window::new("Choose ROM")
+                .open(&mut open)
+                .show(ctx, |ui| {
+                    for entry in &choice.entries {
+                        if ui.button(entry).clicked() {
+                            chosen_entry = Some(entry.clone());
+                        }
+                    }
+                });
+        }
+
+        if let Some(entry) = chosen_entry {
+            let mut choice = archive_choice.take().unwrap();
+            choice.selection.archive_entry = Some(entry);
+            self.rom_filename_sender
+                .send(Some(choice.selection))
+                .unwrap();
+        } else if !open {
+            *archive_choice = None;
+        }
+    }
+}
@@ -1,11 +1,13 @@
 use crate::graphics::gameboy_screen::GameboyScreen;
 
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Mutex};
 
 use crate::config::config_storage::ConfigStorage;
 
 use crate::controls::keyboard_controller::KeyboardController;
-use crate::emulation::Emulation;
+use crate::controls::tilt_controller::TiltController;
+use crate::emulation::{Emulation, RomSelection};
 use crate::graphics::fps_checker::FpsChecker;
 use crate::graphics::gui::emulator_app::EmulatorApp;
 use crate::EmulationSignal;
@@ -13,12 +15,11 @@ use egui::FontDefinitions;
 use egui_wgpu_backend::ScreenDescriptor;
 use egui_winit_platform::PlatformDescriptor;
 use epi::App;
-use rust_boy::lib::gpu::{SCREEN_HEIGHT, SCREEN_WIDTH};
+use rust_boy::lib::gpu::{SCREEN_HEIGHT, SCREEN_WIDTH, SGB_SCREEN_HEIGHT, SGB_SCREEN_WIDTH};
 use rust_boy::lib::io::joypad::Joypad;
 use std::rc::Rc;
-use std::string::String;
 use std::sync::mpsc::{channel, Receiver, Sender};
-use std::thread::sleep;
+use std::thread::{sleep, JoinHandle};
 use std::time::Duration;
 use wgpu::{FilterMode, Surface};
 use winit::dpi::PhysicalSize;
@@ -35,6 +36,7 @@ pub struct GraphicsWindow<'a> {
     height: u32,
     config_storage: &'a ConfigStorage,
     emulation_signal_sender: Option<Rc<Sender<EmulationSignal>>>,
+    emulation_thread: Option<JoinHandle<()>>,
 }
 
 struct ExampleRepaintSignal;
@@ -50,6 +52,7 @@ impl<'a> GraphicsWindow<'a> {
             height,
             config_storage,
             emulation_signal_sender: None,
+            emulation_thread: None,
         }
     }
 
@@ -117,11 +120,38 @@ impl<'a> GraphicsWindow<'a> {
             label: Some("Screen Texture"),
         });
 
-        let joypad = Arc::new(Mutex::new(Joypad::new()));
+        //The SGB draws a border around the game screen
+        let sgb_texture_size = wgpu::Extent3d {
+            width: SGB_SCREEN_WIDTH as u32,
+            height: SGB_SCREEN_HEIGHT as u32,
+            depth_or_array_layers: 1,
+        };
 
-        let emulation = Emulation::new(Arc::clone(&gameboy_screen), Arc::clone(&joypad));
+        let sgb_texture = device.create_texture(&wgpu::TextureDescriptor {
+            size: sgb_texture_size,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::Rgba8UnormSrgb,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            label: Some("SGB Screen Texture"),
+        });
+
+        let joypad = Arc::new(Mutex::new(Joypad::new()));
+        let rumble_active = Arc::new(AtomicBool::new(false));
+        let mut rumble_shown = false;
+        let tilt = Arc::new(Mutex::new((0.0, 0.0)));
+
+        let emulation = Emulation::new(
+            Arc::clone(&gameboy_screen),
+            Arc::clone(&joypad),
+            Arc::clone(&self.config_storage.config),
+            Arc::clone(&rumble_active),
+            Arc::clone(&tilt),
+        );
 
         let keyboard_controller = KeyboardController::new(joypad, &self.config_storage);
+        let tilt_controller = TiltController::new(tilt, &self.config_storage);
 
         let (rom_filename_sender, rom_filename_receiver) = channel();
 
@@ -135,8 +165,11 @@ impl<'a> GraphicsWindow<'a> {
 
         let mut egui_rpass = egui_wgpu_backend::RenderPass::new(&device, config.format, 1);
 
-        let mut emulator_gui_app =
-            EmulatorApp::new(rom_filename_sender, &self.config_storage.config);
+        let mut emulator_gui_app = EmulatorApp::new(
+            rom_filename_sender,
+            &self.config_storage.config,
+            Arc::clone(&gameboy_screen),
+        );
 
         let repaint_signal = std::sync::Arc::new(ExampleRepaintSignal {});
 
@@ -152,16 +185,17 @@ impl<'a> GraphicsWindow<'a> {
             match event {
                 Event::WindowEvent { event, .. } => match event {
                     WindowEvent::CloseRequested => {
-                        if let Some(signal_sender) = &self.emulation_signal_sender {
-                            signal_sender.send(EmulationSignal::Quit).unwrap();
-                        }
+                        self.stop_emulation();
                         *control_flow = ControlFlow::Exit;
                         println!("Closing...");
                         return;
                     }
                     WindowEvent::KeyboardInput { input, .. } => {
                         emulator_gui_app.set_keyboard_input(input);
-                        handle_inputs(&keyboard_controller, &input);
+                        handle_inputs(&keyboard_controller, &tilt_controller, &input);
+                    }
+                    WindowEvent::CursorMoved { position, .. } => {
+                        tilt_controller.move_mouse(position, window.inner_size());
                     }
                     WindowEvent::Resized(physical_size) => {
                         resize(&surface, &mut config, &device, physical_size);
@@ -172,6 +206,17 @@ impl<'a> GraphicsWindow<'a> {
                     _ => {}
                 },
                 Event::MainEventsCleared => {
+                    //Show the rumble motor state in the title since there is no controller to forward it to
+                    let rumble = rumble_active.load(Ordering::Relaxed);
+                    if rumble != rumble_shown {
+                        rumble_shown = rumble;
+                        window.set_title(if rumble {
+                            "RustBoy (Rumble)"
+                        } else {
+                            "RustBoy"
+                        });
+                    }
+
                     let output = surface.get_current_texture().unwrap();
                     let view = output
                         .texture
@@ -195,9 +240,16 @@ impl<'a> GraphicsWindow<'a> {
                         repaint_signal: repaint_signal.clone(),
                     });
 
+                    let sgb_active = gameboy_screen.is_sgb_active();
+                    let texture = if sgb_active {
+                        &sgb_texture
+                    } else {
+                        &screen_texture
+                    };
+
                     emulator_gui_app.set_tex(egui_rpass.egui_texture_from_wgpu_texture(
                         &device,
-                        &screen_texture,
+                        texture,
                         FilterMode::Nearest,
                     ));
 
@@ -226,7 +278,11 @@ impl<'a> GraphicsWindow<'a> {
                         )
                         .unwrap();
 
-                    gameboy_screen.draw_to_queue(&queue, &screen_texture, texture_size);
+                    if sgb_active {
+                        gameboy_screen.draw_sgb_to_queue(&queue, &sgb_texture, sgb_texture_size);
+                    } else {
+                        gameboy_screen.draw_to_queue(&queue, &screen_texture, texture_size);
+                    }
 
                     queue.submit(std::iter::once(encoder.finish()));
                     output.present();
@@ -250,36 +306,67 @@ impl<'a> GraphicsWindow<'a> {
         });
     }
 
+    /// Waits until the running emulation has written its savegame
+    fn stop_emulation(&mut self) {
+        if let Some(sender) = self.emulation_signal_sender.take() {
+            sender.send(EmulationSignal::Quit).unwrap();
+        }
+
+        if let Some(emulation_thread) = self.emulation_thread.take() {
+            if emulation_thread.join().is_err() {
+                println!("Emulation thread panicked");
+            }
+        }
+    }
+
     fn start_emulation(
         &mut self,
-        rom_filename_receiver: &Receiver<Option<String>>,
+        rom_filename_receiver: &Receiver<Option<RomSelection>>,
         emulation: &Emulation,
     ) {
-        if let Ok(filename) = rom_filename_receiver.try_recv() {
-            let rom_file: String;
-            match filename {
-                Some(file) => rom_file = file,
+        if let Ok(selection) = rom_filename_receiver.try_recv() {
+            let rom_selection: RomSelection;
+            match selection {
+                Some(selection) => rom_selection = selection,
                 None => {
                     return;
                 }
             }
-            if let Some(sender) = &self.emulation_signal_sender {
-                //Stop running emulation
-                sender.send(EmulationSignal::Quit).unwrap();
-            }
-
-            let sender = emulation.start(&rom_file).unwrap();
+            self.stop_emulation();
 
-            self.emulation_signal_sender = Some(Rc::new(sender));
+            match emulation.start(&rom_selection) {
+                Ok((sender, emulation_thread)) => {
+                    self.emulation_signal_sender = Some(Rc::new(sender));
+                    self.emulation_thread = Some(emulation_thread);
+                }
+                Err(error) => {
+                    self.emulation_signal_sender = None;
+                    tinyfiledialogs::message_box_ok(
+                        "Could not start game",
+                        &error,
+                        tinyfiledialogs::MessageBoxIcon::Error,
+                    );
+                }
+            }
         }
     }
 }
 
-fn handle_inputs(keyboard_controller: &KeyboardController, input: &KeyboardInput) {
+fn handle_inputs(
+    keyboard_controller: &KeyboardController,
+    tilt_controller: &TiltController,
+    input: &KeyboardInput,
+) {
     if let Some(keycode) = input.virtual_keycode {
         match input.state {
-            winit::event::ElementState::Pressed => keyboard_controller.push_key(keycode),
-            winit::event::ElementState::Released => keyboard_controller.release_key(keycode),
+            winit::event::ElementState::Pressed => {
+                keyboard_controller.push_key(keycode);
+                tilt_controller.push_key(keycode);
+            }
+            winit::event::ElementState::Released => {
+                keyboard_controller.release_key(keycode);
+                tilt_controller.release_key(keycode);
+            }
         }
     }
 }
@@ -7,5 +7,7 @@ pub mod lib {
     pub mod gpu;
     pub mod io;
     pub mod memory;
+    pub mod patch;
+    pub mod sgb;
     pub mod util;
 }
@@ -16,6 +16,8 @@ const WAVE_CHANNEL_END_ADDRESS: u16 = 0xFF1E;
 const NOISE_CHANNEL_START_ADDRESS: u16 = 0xFF1F;
 const NOISE_CHANNEL_END_ADDRESS: u16 = 0xFF23;
 
+const MAX_CYCLES_PER_STEP: u8 = 4;
+
 pub struct Apu<'a> {
     pub audio_output: &'a mut dyn AudioOutput,
     frame_sequencer: FrameSequencer,
@@ -47,10 +49,21 @@ impl<'a> Apu<'a> {
     }
 
     pub fn step(&mut self, clock_cycles: u8) {
+        //Channels advance at most one waveform step per call so longer steps get split up
+        let mut remaining_cycles = clock_cycles;
+
+        while remaining_cycles > 0 {
+            let cycles = remaining_cycles.min(MAX_CYCLES_PER_STEP);
+            self.step_cycles(cycles);
+            remaining_cycles -= cycles;
+        }
+    }
+
+    fn step_cycles(&mut self, clock_cycles: u8) {
         self.clock += clock_cycles as u16;
 
         if self.enbaled {
-            self.frame_sequencer.step(clock_cycles);
+            self.frame_sequencer.step();
 
             self.square_channel1
                 .step(&self.frame_sequencer, clock_cycles);
@@ -75,6 +88,24 @@ impl<'a> Apu<'a> {
         }
     }
 
+    /// While the cpu is stopped the apu is not clocked.
+    /// Silence is still output so the audio stays in sync
+    pub fn step_silent(&mut self, clock_cycles: u8) {
+        self.clock += clock_cycles as u16;
+
+        while self.clock >= self.output_step {
+            self.audio_output.output((0, 0));
+            self.clock -= self.output_step;
+        }
+    }
+
+    /// Clocks the frame sequencer. Driven by the falling edge of bit 4 of DIV
+    pub fn clock_frame_sequencer(&mut self) {
+        if self.enbaled {
+            self.frame_sequencer.clock();
+        }
+    }
+
     pub fn write(&mut self, address: u16, value: u8) {
         match address {
             SQUARE_CHANNEL_1_START_ADDRESS..=SQUARE_CHANNEL_1_END_ADDRESS => {
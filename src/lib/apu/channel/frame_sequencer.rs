@@ -1,16 +1,16 @@
-use crate::lib::emulation::CPU_CLOCK_HZ;
-
-const CYCLES_VOLUME_ENVELOPE_TIMER: u32 = (CPU_CLOCK_HZ / 64) as u32;
-const CYCLES_LENGTH_COUNTER_TIMER: u32 = (CPU_CLOCK_HZ / 256) as u32;
-const CYCLES_SWEEP_TIMER: u32 = (CPU_CLOCK_HZ / 128) as u32;
+//The frame sequencer is clocked at 512 Hz by a falling edge of bit 4 of DIV
+//Step:           0 1 2 3 4 5 6 7
+//Length counter: x   x   x   x
+//Sweep:              x       x
+//Volume envelope:              x
+const STEPS: u8 = 8;
 
 pub struct FrameSequencer {
     pub volume_envelope_trigger: bool,
     pub length_counter_trigger: bool,
     pub sweep_timer_trigger: bool,
-    volume_envelope_timer: u32,
-    length_counter_timer: u32,
-    sweep_timer: u32,
+    step: u8,
+    clocked: bool,
 }
 
 impl FrameSequencer {
@@ -19,49 +19,42 @@ impl FrameSequencer {
             volume_envelope_trigger: false,
             length_counter_trigger: false,
             sweep_timer_trigger: false,
-            volume_envelope_timer: 0,
-            length_counter_timer: 0,
-            sweep_timer: 0,
+            step: 0,
+            clocked: false,
         }
     }
 
-    pub fn step(&mut self, clock_cycles: u8) {
-        cycle_timer(
-            &mut self.volume_envelope_timer,
-            CYCLES_VOLUME_ENVELOPE_TIMER,
-            &mut self.volume_envelope_trigger,
-            clock_cycles,
-        );
+    /// Called by the timer on a falling edge of the DIV bit. The step is applied on the next call of step
+    pub fn clock(&mut self) {
+        self.clocked = true;
+    }
 
-        cycle_timer(
-            &mut self.length_counter_timer,
-            CYCLES_LENGTH_COUNTER_TIMER,
-            &mut self.length_counter_trigger,
-            clock_cycles,
-        );
+    pub fn step(&mut self) {
+        self.volume_envelope_trigger = false;
+        self.length_counter_trigger = false;
+        self.sweep_timer_trigger = false;
 
-        cycle_timer(
-            &mut self.sweep_timer,
-            CYCLES_SWEEP_TIMER,
-            &mut self.sweep_timer_trigger,
-            clock_cycles,
-        );
-    }
+        if !self.clocked {
+            return;
+        }
 
-    pub fn reset(&mut self) {
-        self.volume_envelope_timer = 0;
-        self.length_counter_timer = 0;
-        self.sweep_timer = 0;
-    }
-}
+        self.clocked = false;
 
-fn cycle_timer(timer: &mut u32, limit: u32, trigger: &mut bool, clock_cycles: u8) {
-    *timer += clock_cycles as u32;
+        match self.step {
+            0 | 4 => self.length_counter_trigger = true,
+            2 | 6 => {
+                self.length_counter_trigger = true;
+                self.sweep_timer_trigger = true;
+            }
+            7 => self.volume_envelope_trigger = true,
+            _ => {}
+        }
 
-    if limit <= *timer {
-        *timer -= limit;
-        *trigger = true;
-    } else {
-        *trigger = false;
+        self.step = (self.step + 1) % STEPS;
+    }
+
+    pub fn reset(&mut self) {
+        self.step = 0;
+        self.clocked = false;
     }
 }
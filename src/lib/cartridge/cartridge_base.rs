@@ -1,48 +1,50 @@
-use crate::lib::cartridge::{create_ram, RamDumper, EXT_RAM_ADDRESS, EXT_RAM_SIZE};
+use crate::lib::cartridge::header::CartridgeHeader;
+use crate::lib::cartridge::{
+    create_ram, MappedBanks, SaveDataError, EXT_RAM_ADDRESS, EXT_RAM_SIZE,
+};
+
+const ROM_BANK_SIZE: usize = 0x4000;
 
 pub struct CartridgeBase {
     pub rom: Vec<u8>,
     pub ram: Option<Vec<u8>>,
-    pub rom_bank: u8,
+    pub rom_bank: u16,
     pub ram_bank: u8,
     pub ram_enabled: bool,
+    pub header: CartridgeHeader,
+    //Set when the save data changes
+    pub dirty: bool,
     has_battery: bool,
-    ram_dumper: Option<Box<dyn RamDumper + Send>>,
 }
 
 impl CartridgeBase {
-    pub fn new(
-        rom: Vec<u8>,
-        has_ram: bool,
-        ram_size: Option<usize>,
-        has_battery: bool,
-        ram_dumper: Option<Box<dyn RamDumper + Send>>,
-    ) -> Self {
+    pub fn new(rom: Vec<u8>, has_ram: bool, ram_size: Option<usize>, has_battery: bool) -> Self {
         let ram = if has_ram { create_ram(ram_size) } else { None };
+        //new_cartridge rejects roms without a complete header
+        let header = CartridgeHeader::parse(&rom).expect("Rom is too short for a header");
 
-        let mut base = CartridgeBase {
+        CartridgeBase {
             rom,
             ram,
             rom_bank: 1,
             ram_bank: 0,
             ram_enabled: false,
+            header,
+            dirty: false,
             has_battery,
-            ram_dumper,
-        };
-
-        base.load_savegame();
-        base
+        }
     }
 
     pub fn read(&self, address: u16) -> u8 {
         match address {
-            //Bank 00. Read directly from rom
-            0x0..=0x3FFF => self.rom[address as usize],
+            //Bank 00
+            0x0..=0x3FFF => self.read_rom_bank(0, ROM_BANK_SIZE, address as usize),
             //Bank 01-7F
-            0x4000..=0x7FFF => {
-                let offset = 0x4000 * self.rom_bank as usize;
-                self.rom[(address as usize - 0x4000) + offset]
-            }
+            0x4000..=0x7FFF => self.read_rom_bank(
+                self.rom_bank as usize,
+                ROM_BANK_SIZE,
+                address as usize - 0x4000,
+            ),
             _ => panic!("Address unknown: 0x{:X}", address),
         }
     }
@@ -53,11 +55,12 @@ impl CartridgeBase {
         }
 
         let ram_bank = self.ram_bank as usize;
-
-        if let Some(ref mut ram) = self.ram {
-            let offset = EXT_RAM_SIZE * ram_bank;
-            ram[(address as usize - EXT_RAM_ADDRESS) + offset] = value;
-        }
+        self.write_ram_bank(
+            ram_bank,
+            EXT_RAM_SIZE,
+            address as usize - EXT_RAM_ADDRESS,
+            value,
+        );
     }
 
     pub fn read_ram(&self, address: u16) -> u8 {
@@ -65,39 +68,95 @@ impl CartridgeBase {
             return 0;
         }
 
-        let ram_bank = self.ram_bank as usize;
+        self.read_ram_bank(
+            self.ram_bank as usize,
+            EXT_RAM_SIZE,
+            address as usize - EXT_RAM_ADDRESS,
+        )
+    }
 
-        if let Some(ref ram) = self.ram {
-            let offset = EXT_RAM_SIZE * ram_bank;
-            return ram[(address as usize - EXT_RAM_ADDRESS) + offset];
+    /// Reads from a rom bank of any size. Used by cartridges mapping several smaller windows like the MBC6
+    pub fn read_rom_bank(&self, bank: usize, bank_size: usize, offset: usize) -> u8 {
+        mirrored_index(self.rom.len(), bank, bank_size, offset)
+            .map_or(0xFF, |index| self.rom[index])
+    }
+
+    /// Reads from a ram bank of any size without checking if the RAM is enabled
+    pub fn read_ram_bank(&self, bank: usize, bank_size: usize, offset: usize) -> u8 {
+        match self.ram {
+            Some(ref ram) => {
+                mirrored_index(ram.len(), bank, bank_size, offset).map_or(0, |index| ram[index])
+            }
+            None => 0,
         }
+    }
 
-        0
+    /// Writes to a ram bank of any size without checking if the RAM is enabled
+    pub fn write_ram_bank(&mut self, bank: usize, bank_size: usize, offset: usize, value: u8) {
+        if let Some(ref mut ram) = self.ram {
+            if let Some(index) = mirrored_index(ram.len(), bank, bank_size, offset) {
+                self.dirty |= ram[index] != value;
+                ram[index] = value;
+            }
+        }
     }
 
-    pub fn dump_savegame(&self) {
-        if !self.has_battery {
-            return;
+    pub fn has_battery(&self) -> bool {
+        self.has_battery
+    }
+
+    /// Whether the battery backed RAM changed since the last clear_dirty
+    pub fn is_dirty(&self) -> bool {
+        self.has_battery && self.dirty
+    }
+
+    pub fn mapped_banks(&self) -> MappedBanks {
+        MappedBanks {
+            rom_bank_0: 0,
+            rom_bank: self.rom_bank,
+            ram_bank: self.ram_bank,
         }
+    }
 
-        if let Some(ref ram) = self.ram {
-            if let Some(ref dumper) = self.ram_dumper {
-                dumper.dump(ram)
-            }
+    /// Battery backed RAM. None without battery
+    pub fn save_data(&self) -> Option<&[u8]> {
+        if !self.has_battery {
+            return None;
         }
+
+        self.ram.as_deref()
     }
 
-    pub fn load_savegame(&mut self) {
+    /// Restores the RAM and returns the data stored after it like the RTC
+    pub fn load_save_data<'a>(&mut self, data: &'a [u8]) -> Result<&'a [u8], SaveDataError> {
         if !self.has_battery {
-            return;
+            return Err(SaveDataError::NoBattery);
         }
 
-        if let Some(ref dumper) = self.ram_dumper {
-            if let Some(data) = dumper.load() {
-                if let Some(ref mut ram) = self.ram {
-                    *ram = data;
-                }
-            }
+        let ram_size = self.ram.as_ref().map_or(0, |ram| ram.len());
+        if data.len() < ram_size {
+            return Err(SaveDataError::TooShort {
+                expected: ram_size,
+                actual: data.len(),
+            });
         }
+
+        let (ram_data, extra_data) = data.split_at(ram_size);
+        if let Some(ref mut ram) = self.ram {
+            ram.copy_from_slice(ram_data);
+        }
+
+        Ok(extra_data)
     }
 }
+
+/// Banks beyond the memory size mirror the existing ones like the unconnected address lines on hardware.
+/// Memory smaller than a bank is repeated within the bank
+pub fn mirrored_index(size: usize, bank: usize, bank_size: usize, offset: usize) -> Option<usize> {
+    if size == 0 {
+        return None;
+    }
+
+    let banks = (size / bank_size).max(1);
+    Some(((bank % banks) * bank_size + offset) % size)
+}
@@ -0,0 +1,96 @@
+use crate::lib::cartridge::mmm01::has_menu_at_end;
+use crate::lib::cartridge::sachen::unscramble_address;
+use crate::lib::cartridge::{CARTRIDGE_TYPE_ADDRESS, LOGO_ADDRESS, NINTENDO_LOGO};
+use crate::lib::util::crc32::crc32;
+use std::collections::HashMap;
+
+//M161 multicarts contain 8 games of 32 KiB each with their own header
+const M161_GAMES: usize = 8;
+const M161_GAME_SIZE: usize = 0x8000;
+
+/// Mappers of unlicensed games and multicarts which are not identified by the cartridge type in the header
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum UnlicensedMapper {
+    Mmm01,
+    WisdomTree,
+    Sachen,
+    M161,
+}
+
+/// CRC32 of rom dumps with a known mapper. Checked before the heuristics for dumps
+/// they do not recognize or recognize wrongly
+#[derive(Default)]
+pub struct KnownRoms {
+    mappers: HashMap<u32, UnlicensedMapper>,
+}
+
+impl KnownRoms {
+    pub fn new() -> KnownRoms {
+        KnownRoms {
+            mappers: HashMap::new(),
+        }
+    }
+
+    pub fn insert(&mut self, crc32: u32, mapper: UnlicensedMapper) {
+        self.mappers.insert(crc32, mapper);
+    }
+
+    fn lookup(&self, rom: &[u8]) -> Option<UnlicensedMapper> {
+        //Saves hashing the rom when there is nothing to look up
+        if self.mappers.is_empty() {
+            return None;
+        }
+
+        self.mappers.get(&crc32(rom)).copied()
+    }
+}
+
+/// Detects the mapper by the known roms or by heuristics on the rom contents
+pub fn detect_unlicensed_mapper(rom: &[u8], known_roms: &KnownRoms) -> Option<UnlicensedMapper> {
+    if let Some(mapper) = known_roms.lookup(rom) {
+        Some(mapper)
+    } else if has_menu_at_end(rom) || (0x0B..=0x0D).contains(&rom[CARTRIDGE_TYPE_ADDRESS]) {
+        Some(UnlicensedMapper::Mmm01)
+    } else if is_sachen(rom) {
+        Some(UnlicensedMapper::Sachen)
+    } else if is_m161(rom) {
+        Some(UnlicensedMapper::M161)
+    } else if is_wisdom_tree(rom) {
+        Some(UnlicensedMapper::WisdomTree)
+    } else {
+        None
+    }
+}
+
+fn has_logo_at(rom: &[u8], offset: usize) -> bool {
+    let address = offset + LOGO_ADDRESS;
+    rom.len() >= address + NINTENDO_LOGO.len()
+        && rom[address..address + NINTENDO_LOGO.len()] == NINTENDO_LOGO
+}
+
+/// Sachen games show their own logo. The Nintendo logo is only visible to the boot rom
+/// which reads it from 0x0184 with scrambled address lines
+fn is_sachen(rom: &[u8]) -> bool {
+    if has_logo_at(rom, 0) {
+        return false;
+    }
+
+    NINTENDO_LOGO.iter().enumerate().all(|(index, &value)| {
+        let address = unscramble_address((LOGO_ADDRESS + index) as u16 | 0x80) as usize;
+        rom.get(address) == Some(&value)
+    })
+}
+
+fn is_m161(rom: &[u8]) -> bool {
+    rom.len() == M161_GAMES * M161_GAME_SIZE
+        && (0..M161_GAMES).all(|game| has_logo_at(rom, game * M161_GAME_SIZE))
+}
+
+/// Wisdom Tree games claim to be rom only but are bigger than 32 KiB
+fn is_wisdom_tree(rom: &[u8]) -> bool {
+    let name = b"WISDOM TREE";
+
+    rom.len() > 0x8000
+        && (rom[CARTRIDGE_TYPE_ADDRESS] == 0x00 || rom[CARTRIDGE_TYPE_ADDRESS] == 0xC0)
+        && rom.windows(name.len()).any(|window| window == name)
+}
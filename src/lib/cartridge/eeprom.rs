@@ -0,0 +1,212 @@
+//93LC56 with 128 words of 16 bits
+pub const EEPROM_SIZE: usize = 256;
+const WORDS: usize = EEPROM_SIZE / 2;
+//2 bit opcode followed by 8 address bits
+const COMMAND_BITS: u8 = 10;
+const WORD_BITS: u8 = 16;
+
+//Bits of the register at 0xA080
+const CHIP_SELECT: u8 = 0x80;
+const CLOCK: u8 = 0x40;
+const DATA_IN: u8 = 0x02;
+const DATA_OUT: u8 = 0x01;
+
+#[derive(PartialEq)]
+enum State {
+    //Waiting for the start bit
+    Idle,
+    Command,
+    Read,
+    Write,
+    WriteAll,
+}
+
+/// Serial EEPROM of the MBC7. Bits are shifted in on the rising edge of the clock while chip select is high.
+/// The contents are kept in the cartridge RAM so they are saved like a normal savegame
+pub struct Eeprom {
+    state: State,
+    chip_select: bool,
+    clock: bool,
+    data_in: bool,
+    data_out: bool,
+    write_enabled: bool,
+    shift_register: u16,
+    bits: u8,
+    address: usize,
+}
+
+impl Default for Eeprom {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Eeprom {
+    pub fn new() -> Eeprom {
+        Eeprom {
+            state: State::Idle,
+            chip_select: false,
+            clock: false,
+            data_in: false,
+            data_out: true,
+            write_enabled: false,
+            shift_register: 0,
+            bits: 0,
+            address: 0,
+        }
+    }
+
+    pub fn read(&self) -> u8 {
+        let mut value = 0;
+
+        if self.chip_select {
+            value |= CHIP_SELECT;
+        }
+        if self.clock {
+            value |= CLOCK;
+        }
+        if self.data_in {
+            value |= DATA_IN;
+        }
+        if self.data_out {
+            value |= DATA_OUT;
+        }
+
+        value
+    }
+
+    /// Returns true if the data was modified
+    pub fn write(&mut self, value: u8, data: &mut [u8]) -> bool {
+        let clock = value & CLOCK == CLOCK;
+        let rising_edge = clock && !self.clock;
+
+        self.chip_select = value & CHIP_SELECT == CHIP_SELECT;
+        self.clock = clock;
+        self.data_in = value & DATA_IN == DATA_IN;
+
+        //Lowering chip select aborts the current command
+        if !self.chip_select {
+            self.state = State::Idle;
+            return false;
+        }
+
+        rising_edge && self.clock_in(data)
+    }
+
+    fn clock_in(&mut self, data: &mut [u8]) -> bool {
+        match self.state {
+            State::Idle => {
+                if self.data_in {
+                    self.state = State::Command;
+                    self.shift_register = 0;
+                    self.bits = 0;
+                }
+            }
+            State::Command => {
+                self.shift_in();
+
+                if self.bits == COMMAND_BITS {
+                    return self.execute_command(data);
+                }
+            }
+            //Data is sent MSB first
+            State::Read => {
+                self.data_out = self.shift_register & 0x8000 == 0x8000;
+                self.shift_register <<= 1;
+                self.bits += 1;
+
+                if self.bits == WORD_BITS {
+                    self.state = State::Idle;
+                }
+            }
+            State::Write | State::WriteAll => {
+                self.shift_in();
+
+                if self.bits == WORD_BITS {
+                    let modified = self.write_enabled;
+                    if self.write_enabled {
+                        let addresses = if self.state == State::WriteAll {
+                            0..WORDS
+                        } else {
+                            self.address..self.address + 1
+                        };
+
+                        for address in addresses {
+                            write_word(data, address, self.shift_register);
+                        }
+                    }
+
+                    self.state = State::Idle;
+                    self.data_out = true;
+                    return modified;
+                }
+            }
+        }
+
+        false
+    }
+
+    fn shift_in(&mut self) {
+        self.shift_register = self.shift_register << 1 | self.data_in as u16;
+        self.bits += 1;
+    }
+
+    fn execute_command(&mut self, data: &mut [u8]) -> bool {
+        let opcode = self.shift_register >> 8;
+        let address_bits = self.shift_register & 0xFF;
+        //The highest address bit is ignored with 128 words
+        let address = (address_bits & 0x7F) as usize;
+
+        self.state = State::Idle;
+        self.shift_register = 0;
+        self.bits = 0;
+
+        match opcode {
+            //READ. A dummy 0 is sent before the data
+            0b10 => {
+                self.shift_register = read_word(data, address);
+                self.data_out = false;
+                self.state = State::Read;
+            }
+            //WRITE
+            0b01 => {
+                self.address = address;
+                self.state = State::Write;
+            }
+            //ERASE
+            0b11 => {
+                if self.write_enabled {
+                    write_word(data, address, 0xFFFF);
+                    return true;
+                }
+            }
+            //The upper 2 address bits select the other commands
+            _ => match address_bits >> 6 {
+                //EWDS
+                0b00 => self.write_enabled = false,
+                //WRAL
+                0b01 => self.state = State::WriteAll,
+                //ERAL
+                0b10 => {
+                    if self.write_enabled {
+                        data.fill(0xFF);
+                        return true;
+                    }
+                }
+                //EWEN
+                _ => self.write_enabled = true,
+            },
+        }
+
+        false
+    }
+}
+
+fn read_word(data: &[u8], address: usize) -> u16 {
+    data[address * 2] as u16 | (data[address * 2 + 1] as u16) << 8
+}
+
+fn write_word(data: &mut [u8], address: usize, value: u16) {
+    data[address * 2] = value as u8;
+    data[address * 2 + 1] = (value >> 8) as u8;
+}
@@ -0,0 +1,120 @@
+//Macronix MX29F008 with 1 MiB divided into 8 sectors of 128 KiB
+pub const FLASH_SIZE: usize = 0x100000;
+const SECTOR_SIZE: usize = 0x20000;
+
+//Commands are unlocked by writing 0xAA to 0x5555 and 0x55 to 0x2AAA
+const UNLOCK_ADDRESS_1: usize = 0x5555;
+const UNLOCK_ADDRESS_2: usize = 0x2AAA;
+//Only the lower 15 address bits are compared for the unlock sequence
+const COMMAND_ADDRESS_MASK: usize = 0x7FFF;
+
+const COMMAND_ID: u8 = 0x90;
+const COMMAND_PROGRAM: u8 = 0xA0;
+const COMMAND_ERASE: u8 = 0x80;
+const COMMAND_ERASE_SECTOR: u8 = 0x30;
+const COMMAND_ERASE_CHIP: u8 = 0x10;
+const COMMAND_RESET: u8 = 0xF0;
+
+const MANUFACTURER_ID: u8 = 0xC2;
+const DEVICE_ID: u8 = 0x81;
+
+#[derive(PartialEq, Clone, Copy)]
+enum State {
+    Ready,
+    Unlock1,
+    Unlock2,
+    Program,
+    Erase,
+    EraseUnlock1,
+    EraseUnlock2,
+}
+
+/// Flash chip of the MBC6. Programming can only clear bits, erasing sets all bits of a sector again.
+/// Commands finish instantly so status polling always sees the final data
+pub struct Flash {
+    pub data: Vec<u8>,
+    //Set by programming and erasing
+    pub dirty: bool,
+    state: State,
+    id_mode: bool,
+}
+
+impl Default for Flash {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Flash {
+    pub fn new() -> Flash {
+        Flash {
+            data: vec![0xFF; FLASH_SIZE],
+            dirty: false,
+            state: State::Ready,
+            id_mode: false,
+        }
+    }
+
+    pub fn read(&self, address: usize) -> u8 {
+        let address = address % FLASH_SIZE;
+
+        if self.id_mode {
+            return match address & 0x01 {
+                0 => MANUFACTURER_ID,
+                _ => DEVICE_ID,
+            };
+        }
+
+        self.data[address]
+    }
+
+    /// Program and erase commands are ignored without write_enabled
+    pub fn write(&mut self, address: usize, value: u8, write_enabled: bool) {
+        let address = address % FLASH_SIZE;
+        let command_address = address & COMMAND_ADDRESS_MASK;
+
+        //0xF0 is a normal data byte while programming
+        if value == COMMAND_RESET && self.state != State::Program {
+            self.state = State::Ready;
+            self.id_mode = false;
+            return;
+        }
+
+        self.state = match (self.state, command_address, value) {
+            (State::Ready, UNLOCK_ADDRESS_1, 0xAA) => State::Unlock1,
+            (State::Unlock1, UNLOCK_ADDRESS_2, 0x55) => State::Unlock2,
+            (State::Unlock2, UNLOCK_ADDRESS_1, COMMAND_ID) => {
+                self.id_mode = true;
+                State::Ready
+            }
+            (State::Unlock2, UNLOCK_ADDRESS_1, COMMAND_PROGRAM) => State::Program,
+            (State::Unlock2, UNLOCK_ADDRESS_1, COMMAND_ERASE) => State::Erase,
+            (State::Program, _, _) => {
+                if write_enabled {
+                    self.data[address] &= value;
+                    self.dirty = true;
+                }
+                State::Ready
+            }
+            (State::Erase, UNLOCK_ADDRESS_1, 0xAA) => State::EraseUnlock1,
+            (State::EraseUnlock1, UNLOCK_ADDRESS_2, 0x55) => State::EraseUnlock2,
+            (State::EraseUnlock2, _, COMMAND_ERASE_SECTOR) => {
+                if write_enabled {
+                    let sector_start = address - address % SECTOR_SIZE;
+                    self.data[sector_start..sector_start + SECTOR_SIZE].fill(0xFF);
+                    self.dirty = true;
+                }
+                State::Ready
+            }
+            (State::EraseUnlock2, UNLOCK_ADDRESS_1, COMMAND_ERASE_CHIP) => {
+                if write_enabled {
+                    self.data.fill(0xFF);
+                    self.dirty = true;
+                }
+                State::Ready
+            }
+            //Invalid sequences return to reading
+            _ => State::Ready,
+        };
+    }
+}
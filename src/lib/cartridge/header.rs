@@ -0,0 +1,205 @@
+use crate::lib::cartridge::{
+    get_ram_size, CARTRIDGE_TYPE_ADDRESS, CGB_FLAG_ADDRESS, LOGO_ADDRESS,
+    NEW_LICENSEE_CODE_ADDRESS, NINTENDO_LOGO, OLD_LICENSEE_CODE_ADDRESS, SGB_FLAG_ADDRESS,
+    TITLE_ADDRESS,
+};
+use std::fmt;
+
+const MANUFACTURER_CODE_ADDRESS: usize = 0x13F;
+const ROM_SIZE_ADDRESS: usize = 0x148;
+const DESTINATION_CODE_ADDRESS: usize = 0x14A;
+const VERSION_ADDRESS: usize = 0x14C;
+pub const HEADER_CHECKSUM_ADDRESS: usize = 0x14D;
+const GLOBAL_CHECKSUM_ADDRESS: usize = 0x14E;
+pub const HEADER_END: usize = 0x150;
+//Old licensee code telling that the new licensee code is used
+const USE_NEW_LICENSEE_CODE: u8 = 0x33;
+
+/// Problems found while parsing or validating the header
+#[derive(Clone, Debug, PartialEq)]
+pub enum HeaderError {
+    RomTooShort(usize),
+    InvalidLogo,
+    UnknownRomSize(u8),
+    RomSizeMismatch { header: usize, file: usize },
+    HeaderChecksumMismatch { header: u8, calculated: u8 },
+    GlobalChecksumMismatch { header: u16, calculated: u16 },
+}
+
+impl fmt::Display for HeaderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HeaderError::RomTooShort(size) => write!(
+                f,
+                "ROM is too short for a header: {} bytes, at least {} needed",
+                size, HEADER_END
+            ),
+            HeaderError::InvalidLogo => write!(f, "Nintendo logo is invalid"),
+            HeaderError::UnknownRomSize(value) => write!(f, "Unknown ROM size: 0x{:02X}", value),
+            HeaderError::RomSizeMismatch { header, file } => write!(
+                f,
+                "ROM size is {} bytes but the header expects {} bytes",
+                file, header
+            ),
+            HeaderError::HeaderChecksumMismatch { header, calculated } => write!(
+                f,
+                "Header checksum is 0x{:02X} but should be 0x{:02X}",
+                header, calculated
+            ),
+            HeaderError::GlobalChecksumMismatch { header, calculated } => write!(
+                f,
+                "Global checksum is 0x{:04X} but should be 0x{:04X}",
+                header, calculated
+            ),
+        }
+    }
+}
+
+impl std::error::Error for HeaderError {}
+
+/// Information about the game from 0x0100-0x014F of the rom
+#[derive(Clone, Debug)]
+pub struct CartridgeHeader {
+    pub title: String,
+    //Only present in newer games which shortened the title to 11 characters
+    pub manufacturer_code: Option<String>,
+    pub cgb_flag: u8,
+    pub sgb_flag: u8,
+    //Two characters for newer games, the old licensee code in hex otherwise
+    pub licensee_code: String,
+    pub cartridge_type: u8,
+    pub rom_size: Option<usize>,
+    pub ram_size: Option<usize>,
+    pub destination_code: u8,
+    pub version: u8,
+    pub header_checksum: u8,
+    pub global_checksum: u16,
+    pub logo_valid: bool,
+}
+
+impl CartridgeHeader {
+    pub fn parse(rom: &[u8]) -> Result<CartridgeHeader, HeaderError> {
+        if rom.len() < HEADER_END {
+            return Err(HeaderError::RomTooShort(rom.len()));
+        }
+
+        let cgb_flag = rom[CGB_FLAG_ADDRESS];
+        let manufacturer_code = &rom[MANUFACTURER_CODE_ADDRESS..CGB_FLAG_ADDRESS];
+        let has_manufacturer_code = cgb_flag & 0x80 == 0x80
+            && manufacturer_code
+                .iter()
+                .all(|character| character.is_ascii_uppercase() || character.is_ascii_digit());
+        let title_end = if has_manufacturer_code {
+            MANUFACTURER_CODE_ADDRESS
+        } else {
+            CGB_FLAG_ADDRESS
+        };
+
+        let old_licensee_code = rom[OLD_LICENSEE_CODE_ADDRESS];
+        let licensee_code = if old_licensee_code == USE_NEW_LICENSEE_CODE {
+            to_text(&rom[NEW_LICENSEE_CODE_ADDRESS..NEW_LICENSEE_CODE_ADDRESS + 2])
+        } else {
+            format!("{:02X}", old_licensee_code)
+        };
+
+        Ok(CartridgeHeader {
+            title: to_text(&rom[TITLE_ADDRESS..title_end]),
+            manufacturer_code: has_manufacturer_code.then(|| to_text(manufacturer_code)),
+            cgb_flag,
+            sgb_flag: rom[SGB_FLAG_ADDRESS],
+            licensee_code,
+            cartridge_type: rom[CARTRIDGE_TYPE_ADDRESS],
+            rom_size: rom_size(rom[ROM_SIZE_ADDRESS]),
+            ram_size: get_ram_size(rom),
+            destination_code: rom[DESTINATION_CODE_ADDRESS],
+            version: rom[VERSION_ADDRESS],
+            header_checksum: rom[HEADER_CHECKSUM_ADDRESS],
+            global_checksum: u16::from_be_bytes([
+                rom[GLOBAL_CHECKSUM_ADDRESS],
+                rom[GLOBAL_CHECKSUM_ADDRESS + 1],
+            ]),
+            logo_valid: rom[LOGO_ADDRESS..LOGO_ADDRESS + NINTENDO_LOGO.len()] == NINTENDO_LOGO,
+        })
+    }
+
+    /// Checks the header against the rom it was parsed from
+    pub fn validate(&self, rom: &[u8]) -> Vec<HeaderError> {
+        let mut errors = Vec::new();
+
+        if !self.logo_valid {
+            errors.push(HeaderError::InvalidLogo);
+        }
+
+        match self.rom_size {
+            Some(size) if size != rom.len() => errors.push(HeaderError::RomSizeMismatch {
+                header: size,
+                file: rom.len(),
+            }),
+            Some(_) => {}
+            None => errors.push(HeaderError::UnknownRomSize(rom[ROM_SIZE_ADDRESS])),
+        }
+
+        let calculated = header_checksum(rom);
+        if calculated != self.header_checksum {
+            errors.push(HeaderError::HeaderChecksumMismatch {
+                header: self.header_checksum,
+                calculated,
+            });
+        }
+
+        let calculated = global_checksum(rom);
+        if calculated != self.global_checksum {
+            errors.push(HeaderError::GlobalChecksumMismatch {
+                header: self.global_checksum,
+                calculated,
+            });
+        }
+
+        errors
+    }
+
+    pub fn supports_cgb(&self) -> bool {
+        self.cgb_flag & 0x80 == 0x80
+    }
+
+    pub fn supports_sgb(&self) -> bool {
+        self.sgb_flag == 0x03
+    }
+}
+
+fn to_text(bytes: &[u8]) -> String {
+    bytes
+        .iter()
+        .take_while(|&&character| character != 0)
+        .map(|&character| character as char)
+        .collect()
+}
+
+/// 32 KiB shifted left by the value
+fn rom_size(value: u8) -> Option<usize> {
+    match value {
+        0x00..=0x08 => Some(0x8000 << value),
+        _ => None,
+    }
+}
+
+/// Checked by the boot rom over 0x0134-0x014C
+pub fn header_checksum(rom: &[u8]) -> u8 {
+    rom[TITLE_ADDRESS..HEADER_CHECKSUM_ADDRESS]
+        .iter()
+        .fold(0u8, |checksum, &value| {
+            checksum.wrapping_sub(value).wrapping_sub(1)
+        })
+}
+
+/// Sum of all bytes except the global checksum itself
+pub fn global_checksum(rom: &[u8]) -> u16 {
+    rom.iter()
+        .enumerate()
+        .filter(|(address, _)| {
+            *address != GLOBAL_CHECKSUM_ADDRESS && *address != GLOBAL_CHECKSUM_ADDRESS + 1
+        })
+        .fold(0u16, |checksum, (_, &value)| {
+            checksum.wrapping_add(value as u16)
+        })
+}
@@ -0,0 +1,78 @@
+use crate::lib::cartridge::cartridge_base::CartridgeBase;
+use crate::lib::cartridge::infrared::InfraredPort;
+use crate::lib::cartridge::{get_ram_size, Cartridge, Infrared};
+
+//Value written to 0x0000-0x1FFF to map the IR port instead of the RAM
+const IR_MODE: u8 = 0x0E;
+
+pub struct HuC1 {
+    cartridge_base: CartridgeBase,
+    ir_mode: bool,
+    infrared_port: InfraredPort,
+}
+
+impl HuC1 {
+    pub fn new(rom: Vec<u8>, infrared: Option<Box<dyn Infrared + Send>>) -> Self {
+        let ram_size = get_ram_size(&rom);
+
+        let mut cartridge_base = CartridgeBase::new(rom, true, ram_size, true);
+        //The RAM can not be disabled, only replaced by the IR port
+        cartridge_base.ram_enabled = true;
+
+        HuC1 {
+            cartridge_base,
+            ir_mode: false,
+            infrared_port: InfraredPort::new(infrared),
+        }
+    }
+}
+
+impl Cartridge for HuC1 {
+    fn read(&self, address: u16) -> u8 {
+        self.cartridge_base.read(address)
+    }
+
+    fn write(&mut self, address: u16, value: u8) {
+        match address {
+            0x0..=0x1FFF => {
+                self.ir_mode = value & 0x0F == IR_MODE;
+            }
+            //Address range for rom bank number
+            0x2000..=0x3FFF => {
+                //0 is also 1
+                let bank_number = value & 0x3F;
+                self.cartridge_base.rom_bank =
+                    if bank_number == 0 { 1 } else { bank_number } as u16;
+            }
+            //Address range for RAM bank number
+            0x4000..=0x5FFF => {
+                self.cartridge_base.ram_bank = value & 0x03;
+            }
+            _ => {}
+        }
+    }
+
+    fn write_ram(&mut self, address: u16, value: u8) {
+        if self.ir_mode {
+            self.infrared_port.write(value);
+        } else {
+            self.cartridge_base.write_ram(address, value);
+        }
+    }
+
+    fn read_ram(&self, address: u16) -> u8 {
+        if self.ir_mode {
+            self.infrared_port.read()
+        } else {
+            self.cartridge_base.read_ram(address)
+        }
+    }
+
+    fn base(&self) -> &CartridgeBase {
+        &self.cartridge_base
+    }
+
+    fn base_mut(&mut self) -> &mut CartridgeBase {
+        &mut self.cartridge_base
+    }
+}
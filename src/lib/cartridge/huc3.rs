@@ -0,0 +1,262 @@
+use crate::lib::cartridge::cartridge_base::CartridgeBase;
+use crate::lib::cartridge::infrared::InfraredPort;
+use crate::lib::cartridge::{get_ram_size, Cartridge, Infrared, SaveDataError};
+use crate::lib::emulation::CPU_CLOCK_HZ;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+//Modes selected by writing to 0x0000-0x1FFF
+const MODE_RAM_READ: u8 = 0x00;
+const MODE_RAM_READ_WRITE: u8 = 0x0A;
+const MODE_COMMAND_WRITE: u8 = 0x0B;
+const MODE_COMMAND_READ: u8 = 0x0C;
+const MODE_SEMAPHORE: u8 = 0x0D;
+const MODE_IR: u8 = 0x0E;
+
+//Commands are written to bits 4-6, the argument to bits 0-3
+const COMMAND_READ: u8 = 0x1;
+const COMMAND_WRITE: u8 = 0x2;
+const COMMAND_WRITE_INCREMENT: u8 = 0x3;
+const COMMAND_ADDRESS_LOW: u8 = 0x4;
+const COMMAND_ADDRESS_HIGH: u8 = 0x5;
+const COMMAND_EXTENDED: u8 = 0x6;
+//Extended command after which reads return 1
+const EXTENDED_STATUS: u8 = 0x2;
+
+const MINUTES_PER_DAY: u64 = 1440;
+const CYCLES_PER_MINUTE: usize = CPU_CLOCK_HZ * 60;
+//Minutes and days are 12 bit counters
+const COUNTER_MASK: u16 = 0xFFF;
+
+//Footer appended to the savegame: UNIX timestamp, minutes, days, alarm minutes, alarm days and alarm enabled
+const RTC_FOOTER_SIZE: usize = 17;
+
+/// Clock of the HuC3 counting minutes of the day and days. Its registers are accessed nibble by nibble
+struct HuC3Clock {
+    minutes: u16,
+    days: u16,
+    alarm_minutes: u16,
+    alarm_days: u16,
+    alarm_enabled: bool,
+    //Clock cycles since the last minute
+    cycles: usize,
+}
+
+impl HuC3Clock {
+    fn new() -> HuC3Clock {
+        HuC3Clock {
+            minutes: 0,
+            days: 0,
+            alarm_minutes: 0,
+            alarm_days: 0,
+            alarm_enabled: false,
+            cycles: 0,
+        }
+    }
+
+    fn tick(&mut self, clock_cycles: u32) {
+        self.cycles += clock_cycles as usize;
+
+        while self.cycles >= CYCLES_PER_MINUTE {
+            self.cycles -= CYCLES_PER_MINUTE;
+            self.advance_minutes(1);
+        }
+    }
+
+    fn advance_minutes(&mut self, minutes: u64) {
+        let total = self.minutes as u64 + minutes;
+        self.minutes = (total % MINUTES_PER_DAY) as u16;
+        self.days = (self.days as u64 + total / MINUTES_PER_DAY) as u16 & COUNTER_MASK;
+    }
+
+    /// Index 0-2 holds the minutes and 3-5 the days, lowest nibble first
+    fn read_nibble(&self, index: u8) -> u8 {
+        match index {
+            0x00..=0x02 => (self.minutes >> (index * 4)) as u8 & 0x0F,
+            0x03..=0x05 => (self.days >> ((index - 3) * 4)) as u8 & 0x0F,
+            _ => 0,
+        }
+    }
+
+    /// Returns whether a register changed
+    fn write_nibble(&mut self, index: u8, value: u8) -> bool {
+        let value = (value & 0x0F) as u16;
+
+        match index {
+            0x00..=0x02 => set_nibble(&mut self.minutes, index, value),
+            0x03..=0x05 => set_nibble(&mut self.days, index - 0x03, value),
+            0x58..=0x5A => set_nibble(&mut self.alarm_minutes, index - 0x58, value),
+            0x5B..=0x5D => set_nibble(&mut self.alarm_days, index - 0x5B, value),
+            0x5F => {
+                let alarm_enabled = value & 0x01 == 0x01;
+                let changed = self.alarm_enabled != alarm_enabled;
+                self.alarm_enabled = alarm_enabled;
+                changed
+            }
+            _ => false,
+        }
+    }
+
+    fn to_footer(&self) -> Vec<u8> {
+        let mut footer = Vec::with_capacity(RTC_FOOTER_SIZE);
+        footer.extend_from_slice(&current_timestamp().to_le_bytes());
+        footer.extend_from_slice(&self.minutes.to_le_bytes());
+        footer.extend_from_slice(&self.days.to_le_bytes());
+        footer.extend_from_slice(&self.alarm_minutes.to_le_bytes());
+        footer.extend_from_slice(&self.alarm_days.to_le_bytes());
+        footer.push(self.alarm_enabled as u8);
+        footer
+    }
+
+    /// Restores the clock and catches up with the time passed since saving
+    fn load_footer(&mut self, footer: &[u8]) {
+        if footer.len() != RTC_FOOTER_SIZE {
+            return;
+        }
+
+        let read_u16 = |offset: usize| u16::from_le_bytes([footer[offset], footer[offset + 1]]);
+        let mut timestamp = [0; 8];
+        timestamp.copy_from_slice(&footer[..8]);
+
+        self.minutes = read_u16(8) % MINUTES_PER_DAY as u16;
+        self.days = read_u16(10) & COUNTER_MASK;
+        self.alarm_minutes = read_u16(12);
+        self.alarm_days = read_u16(14);
+        self.alarm_enabled = footer[16] & 0x01 == 0x01;
+
+        let elapsed_seconds = current_timestamp().saturating_sub(u64::from_le_bytes(timestamp));
+        self.advance_minutes(elapsed_seconds / 60);
+    }
+}
+
+fn set_nibble(register: &mut u16, nibble: u8, value: u16) -> bool {
+    let shift = nibble * 4;
+    let previous = *register;
+    *register = *register & !(0x0F << shift) | value << shift;
+    *register != previous
+}
+
+fn current_timestamp() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_secs())
+}
+
+pub struct HuC3 {
+    cartridge_base: CartridgeBase,
+    mode: u8,
+    clock: HuC3Clock,
+    //Nibble address for the clock commands
+    access_index: u8,
+    access_flags: u8,
+    response: u8,
+    infrared_port: InfraredPort,
+}
+
+impl HuC3 {
+    pub fn new(rom: Vec<u8>, infrared: Option<Box<dyn Infrared + Send>>) -> Self {
+        let ram_size = get_ram_size(&rom);
+
+        let cartridge_base = CartridgeBase::new(rom, true, ram_size, true);
+
+        HuC3 {
+            cartridge_base,
+            mode: MODE_RAM_READ,
+            clock: HuC3Clock::new(),
+            access_index: 0,
+            access_flags: 0,
+            response: 0,
+            infrared_port: InfraredPort::new(infrared),
+        }
+    }
+
+    fn execute_command(&mut self, value: u8) {
+        let argument = value & 0x0F;
+
+        match (value >> 4) & 0x07 {
+            COMMAND_READ => {
+                self.response = self.clock.read_nibble(self.access_index);
+                self.access_index = self.access_index.wrapping_add(1);
+            }
+            //The clock is saved with the RAM
+            COMMAND_WRITE => {
+                self.cartridge_base.dirty |= self.clock.write_nibble(self.access_index, argument);
+            }
+            COMMAND_WRITE_INCREMENT => {
+                self.cartridge_base.dirty |= self.clock.write_nibble(self.access_index, argument);
+                self.access_index = self.access_index.wrapping_add(1);
+            }
+            COMMAND_ADDRESS_LOW => self.access_index = self.access_index & 0xF0 | argument,
+            COMMAND_ADDRESS_HIGH => self.access_index = self.access_index & 0x0F | argument << 4,
+            COMMAND_EXTENDED => self.access_flags = argument,
+            _ => {}
+        }
+    }
+}
+
+impl Cartridge for HuC3 {
+    fn read(&self, address: u16) -> u8 {
+        self.cartridge_base.read(address)
+    }
+
+    fn write(&mut self, address: u16, value: u8) {
+        match address {
+            0x0..=0x1FFF => {
+                self.mode = value & 0x0F;
+                self.cartridge_base.ram_enabled =
+                    self.mode == MODE_RAM_READ || self.mode == MODE_RAM_READ_WRITE;
+            }
+            //Address range for rom bank number
+            0x2000..=0x3FFF => {
+                self.cartridge_base.rom_bank = (value & 0x7F) as u16;
+            }
+            //Address range for RAM bank number
+            0x4000..=0x5FFF => {
+                self.cartridge_base.ram_bank = value & 0x03;
+            }
+            _ => {}
+        }
+    }
+
+    fn write_ram(&mut self, address: u16, value: u8) {
+        match self.mode {
+            MODE_RAM_READ_WRITE => self.cartridge_base.write_ram(address, value),
+            MODE_COMMAND_WRITE => self.execute_command(value),
+            MODE_IR => self.infrared_port.write(value),
+            _ => {}
+        }
+    }
+
+    fn read_ram(&self, address: u16) -> u8 {
+        match self.mode {
+            MODE_RAM_READ | MODE_RAM_READ_WRITE => self.cartridge_base.read_ram(address),
+            MODE_COMMAND_READ if self.access_flags == EXTENDED_STATUS => 0x01,
+            MODE_COMMAND_READ => self.response,
+            //The clock is always ready
+            MODE_SEMAPHORE => 0x01,
+            MODE_IR => self.infrared_port.read(),
+            _ => 0xFF,
+        }
+    }
+
+    fn base(&self) -> &CartridgeBase {
+        &self.cartridge_base
+    }
+
+    fn base_mut(&mut self) -> &mut CartridgeBase {
+        &mut self.cartridge_base
+    }
+
+    fn extra_save_data(&self) -> Option<Vec<u8>> {
+        Some(self.clock.to_footer())
+    }
+
+    fn load_save_data(&mut self, data: &[u8]) -> Result<(), SaveDataError> {
+        let footer = self.cartridge_base.load_save_data(data)?;
+        self.clock.load_footer(footer);
+        Ok(())
+    }
+
+    fn tick(&mut self, clock_cycles: u32) {
+        self.clock.tick(clock_cycles);
+    }
+}
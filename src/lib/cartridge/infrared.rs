@@ -0,0 +1,41 @@
+use crate::lib::cartridge::Infrared;
+
+//Reads of the port have the upper 2 bits set, bit 0 tells if light is detected
+const NO_LIGHT: u8 = 0xC0;
+const LIGHT_DETECTED: u8 = 0xC1;
+
+/// IR LED and receiver mapped to 0xA000-0xBFFF in IR mode of Hudson cartridges
+pub struct InfraredPort {
+    infrared: Option<Box<dyn Infrared + Send>>,
+    led_on: bool,
+}
+
+impl InfraredPort {
+    pub fn new(infrared: Option<Box<dyn Infrared + Send>>) -> InfraredPort {
+        InfraredPort {
+            infrared,
+            led_on: false,
+        }
+    }
+
+    pub fn read(&self) -> u8 {
+        match self.infrared {
+            Some(ref infrared) if infrared.is_light_detected() => LIGHT_DETECTED,
+            _ => NO_LIGHT,
+        }
+    }
+
+    /// Bit 0 turns the LED on
+    pub fn write(&mut self, value: u8) {
+        let led_on = value & 0x01 == 0x01;
+        if led_on == self.led_on {
+            return;
+        }
+
+        self.led_on = led_on;
+
+        if let Some(ref infrared) = self.infrared {
+            infrared.set_led(led_on);
+        }
+    }
+}
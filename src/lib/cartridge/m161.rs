@@ -0,0 +1,58 @@
+use crate::lib::cartridge::cartridge_base::CartridgeBase;
+use crate::lib::cartridge::{Cartridge, MappedBanks};
+
+const ROM_BANK_SIZE: usize = 0x8000;
+
+/// Mapper of M161 multicarts. The first write to 0x4000-0x5FFF selects the 32 KiB bank of a game,
+/// later writes are ignored until reset
+pub struct M161 {
+    cartridge_base: CartridgeBase,
+    rom_bank: usize,
+    locked: bool,
+}
+
+impl M161 {
+    pub fn new(rom: Vec<u8>) -> Self {
+        M161 {
+            cartridge_base: CartridgeBase::new(rom, false, None, false),
+            rom_bank: 0,
+            locked: false,
+        }
+    }
+}
+
+impl Cartridge for M161 {
+    fn read(&self, address: u16) -> u8 {
+        self.cartridge_base
+            .read_rom_bank(self.rom_bank, ROM_BANK_SIZE, address as usize)
+    }
+
+    fn write(&mut self, address: u16, value: u8) {
+        if (0x4000..=0x5FFF).contains(&address) && !self.locked {
+            self.locked = true;
+            self.rom_bank = (value & 0x07) as usize;
+        }
+    }
+
+    fn write_ram(&mut self, _address: u16, _value: u8) {}
+
+    fn read_ram(&self, _address: u16) -> u8 {
+        0xFF
+    }
+
+    fn base(&self) -> &CartridgeBase {
+        &self.cartridge_base
+    }
+
+    fn base_mut(&mut self) -> &mut CartridgeBase {
+        &mut self.cartridge_base
+    }
+
+    fn mapped_banks(&self) -> MappedBanks {
+        MappedBanks {
+            rom_bank_0: (self.rom_bank * 2) as u16,
+            rom_bank: (self.rom_bank * 2 + 1) as u16,
+            ram_bank: 0,
+        }
+    }
+}
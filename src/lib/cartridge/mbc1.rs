@@ -1,5 +1,13 @@
 use crate::lib::cartridge::cartridge_base::CartridgeBase;
-use crate::lib::cartridge::{get_ram_size, Cartridge, RamDumper, CARTRIDGE_TYPE_ADDRESS};
+use crate::lib::cartridge::{
+    get_ram_size, Cartridge, MappedBanks, CARTRIDGE_TYPE_ADDRESS, EXT_RAM_SIZE, LOGO_ADDRESS,
+    NINTENDO_LOGO,
+};
+
+const ROM_BANK_SIZE: usize = 0x4000;
+//Multicarts are 1 MiB and contain a game with its own header every 16 banks
+const MULTICART_ROM_SIZE: usize = 0x100000;
+const MULTICART_GAME_BANKS: usize = 0x10;
 
 enum Mode {
     RomBankingMode,
@@ -9,59 +17,116 @@ enum Mode {
 pub struct Mbc1 {
     cartridge_base: CartridgeBase,
     selected_mode: Mode,
+    //Lower 5 bits of the rom bank
+    bank1: u8,
+    //2 bits used as upper rom bank bits or ram bank
+    bank2: u8,
+    //Bank mapped to 0x0000-0x3FFF
+    rom_bank_0: u16,
+    //MBC1M multicarts only connect 4 bits of bank1, bank2 is shifted by 4
+    is_multicart: bool,
 }
 
 impl Mbc1 {
-    pub fn new(rom: Vec<u8>, ram_dumper: Option<Box<dyn RamDumper + Send>>) -> Self {
+    pub fn new(rom: Vec<u8>) -> Self {
         let cartridge_type = rom[CARTRIDGE_TYPE_ADDRESS];
         let has_ram = cartridge_type == 0x02 || cartridge_type == 0x03;
         let has_battery = cartridge_type == 0x03;
         let ram_size = get_ram_size(&rom);
+        let is_multicart = is_multicart(&rom);
 
-        let cartridge_base = CartridgeBase::new(rom, has_ram, ram_size, has_battery, ram_dumper);
+        let cartridge_base = CartridgeBase::new(rom, has_ram, ram_size, has_battery);
 
         Mbc1 {
             cartridge_base,
             selected_mode: Mode::RomBankingMode,
+            bank1: 1,
+            bank2: 0,
+            rom_bank_0: 0,
+            is_multicart,
+        }
+    }
+
+    /// Maps the banks selected by bank1, bank2 and the mode. Banks beyond the rom and ram size wrap around
+    fn update_banks(&mut self) {
+        let bank2_shift = if self.is_multicart { 4 } else { 5 };
+        let bank1 = if self.is_multicart {
+            self.bank1 & 0x0F
+        } else {
+            self.bank1
+        };
+        let upper_bits = (self.bank2 as u16) << bank2_shift;
+        let rom_bank_mask = (self.cartridge_base.rom.len() / ROM_BANK_SIZE).max(1) as u16 - 1;
+
+        self.cartridge_base.rom_bank = (upper_bits | bank1 as u16) & rom_bank_mask;
+
+        match self.selected_mode {
+            Mode::RomBankingMode => {
+                self.rom_bank_0 = 0;
+                self.cartridge_base.ram_bank = 0;
+            }
+            Mode::RamBankingMode => {
+                let ram_banks = self
+                    .cartridge_base
+                    .ram
+                    .as_ref()
+                    .map_or(0, |ram| ram.len() / EXT_RAM_SIZE);
+
+                self.rom_bank_0 = upper_bits & rom_bank_mask;
+                self.cartridge_base.ram_bank = self.bank2 & (ram_banks.max(1) as u8 - 1);
+            }
         }
     }
 }
 
+/// MBC1M multicarts are detected by the Nintendo logo of the second game at bank 0x10
+fn is_multicart(rom: &[u8]) -> bool {
+    if rom.len() != MULTICART_ROM_SIZE {
+        return false;
+    }
+
+    let second_logo_address = MULTICART_GAME_BANKS * ROM_BANK_SIZE + LOGO_ADDRESS;
+
+    rom[second_logo_address..second_logo_address + NINTENDO_LOGO.len()] == NINTENDO_LOGO
+}
+
 impl Cartridge for Mbc1 {
     fn read(&self, address: u16) -> u8 {
-        self.cartridge_base.read(address)
+        match address {
+            0x0..=0x3FFF => self.cartridge_base.read_rom_bank(
+                self.rom_bank_0 as usize,
+                ROM_BANK_SIZE,
+                address as usize,
+            ),
+            _ => self.cartridge_base.read(address),
+        }
     }
 
     fn write(&mut self, address: u16, value: u8) {
         match address {
             0x0..=0x1FFF => {
-                self.cartridge_base.ram_enabled = value == 0x0A;
+                self.cartridge_base.ram_enabled = value & 0x0F == 0x0A;
             }
             //Address range for rom bank number
             0x2000..=0x3FFF => {
-                //0 is also 1
-                let bank_number = if value == 0 { 1 } else { value };
-                //Only set lower 5 bits
-                self.cartridge_base.rom_bank =
-                    self.cartridge_base.rom_bank & 0x60 | bank_number & 0x1F;
+                //0 is also 1. Only the lower 5 bits are checked so 0x20, 0x40 and 0x60 map to the next bank
+                let bank_number = value & 0x1F;
+                self.bank1 = if bank_number == 0 { 1 } else { bank_number };
+                self.update_banks();
+            }
+            //Address range for RAM bank number or upper 2 bits of the rom bank number
+            0x4000..=0x5FFF => {
+                self.bank2 = value & 0x03;
+                self.update_banks();
             }
-            //Address range for RAM bank number
-            0x4000..=0x5FFF => match self.selected_mode {
-                Mode::RamBankingMode => {
-                    self.cartridge_base.ram_bank = value;
-                }
-                Mode::RomBankingMode => {
-                    //Only set upper 2 bits
-                    self.cartridge_base.rom_bank =
-                        self.cartridge_base.rom_bank | (value & 0x03) << 5;
-                }
-            },
             //Select Mode
-            0x6000..=0x7FFF => match value {
-                0 => self.selected_mode = Mode::RomBankingMode,
-                1 => self.selected_mode = Mode::RamBankingMode,
-                _ => {}
-            },
+            0x6000..=0x7FFF => {
+                self.selected_mode = match value & 0x01 {
+                    0 => Mode::RomBankingMode,
+                    _ => Mode::RamBankingMode,
+                };
+                self.update_banks();
+            }
             _ => {}
         }
     }
@@ -74,11 +139,18 @@ impl Cartridge for Mbc1 {
         self.cartridge_base.read_ram(address)
     }
 
-    fn dump_savegame(&self) {
-        self.cartridge_base.dump_savegame();
+    fn base(&self) -> &CartridgeBase {
+        &self.cartridge_base
     }
 
-    fn load_savegame(&mut self) {
-        self.cartridge_base.load_savegame();
+    fn base_mut(&mut self) -> &mut CartridgeBase {
+        &mut self.cartridge_base
+    }
+
+    fn mapped_banks(&self) -> MappedBanks {
+        MappedBanks {
+            rom_bank_0: self.rom_bank_0,
+            ..self.cartridge_base.mapped_banks()
+        }
     }
 }
@@ -1,16 +1,16 @@
 use crate::lib::cartridge::cartridge_base::CartridgeBase;
-use crate::lib::cartridge::{Cartridge, RamDumper, CARTRIDGE_TYPE_ADDRESS};
+use crate::lib::cartridge::{Cartridge, CARTRIDGE_TYPE_ADDRESS};
 
 pub struct Mbc2 {
     cartridge_base: CartridgeBase,
 }
 
 impl Mbc2 {
-    pub fn new(rom: Vec<u8>, ram_dumper: Option<Box<dyn RamDumper + Send>>) -> Self {
+    pub fn new(rom: Vec<u8>) -> Self {
         let cartridge_type = rom[CARTRIDGE_TYPE_ADDRESS];
         let has_battery = cartridge_type == 0x06;
 
-        let cartridge_base = CartridgeBase::new(rom, true, Some(512), has_battery, ram_dumper);
+        let cartridge_base = CartridgeBase::new(rom, true, Some(512), has_battery);
 
         Mbc2 { cartridge_base }
     }
@@ -35,33 +35,27 @@ impl Cartridge for Mbc2 {
 
                 let bank_number = if value == 0 { 1 } else { value };
 
-                self.cartridge_base.rom_bank = bank_number & 0xF;
+                self.cartridge_base.rom_bank = (bank_number & 0xF) as u16;
             }
             _ => {}
         }
     }
 
+    /// The 512 half bytes are mirrored across 0xA000-0xBFFF
     fn write_ram(&mut self, address: u16, value: u8) {
-        match address {
-            0xA000..=0xA1FF => {
-                self.cartridge_base.write_ram(address, value & 0xF);
-            }
-            _ => {}
-        }
+        self.cartridge_base
+            .write_ram(0xA000 | (address & 0x1FF), value & 0xF);
     }
 
     fn read_ram(&self, address: u16) -> u8 {
-        match address {
-            0xA000..=0xA1FF => self.cartridge_base.read_ram(address) & 0xF,
-            _ => 0,
-        }
+        self.cartridge_base.read_ram(0xA000 | (address & 0x1FF)) & 0xF
     }
 
-    fn dump_savegame(&self) {
-        self.cartridge_base.dump_savegame();
+    fn base(&self) -> &CartridgeBase {
+        &self.cartridge_base
     }
 
-    fn load_savegame(&mut self) {
-        self.cartridge_base.load_savegame();
+    fn base_mut(&mut self) -> &mut CartridgeBase {
+        &mut self.cartridge_base
     }
 }
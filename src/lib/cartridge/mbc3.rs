@@ -1,7 +1,6 @@
 use crate::lib::cartridge::cartridge_base::CartridgeBase;
-use crate::lib::cartridge::{get_ram_size, Cartridge, RamDumper, CARTRIDGE_TYPE_ADDRESS};
-
-use super::regions::{ERAM_REGION_END, ERAM_REGION_START};
+use crate::lib::cartridge::rtc::{Rtc, RTC_DAYS_HIGH, RTC_SECONDS};
+use crate::lib::cartridge::{get_ram_size, Cartridge, SaveDataError, CARTRIDGE_TYPE_ADDRESS};
 
 // enum Mode {
 //     RomBankingMode,
@@ -11,26 +10,26 @@ use super::regions::{ERAM_REGION_END, ERAM_REGION_START};
 pub struct Mbc3 {
     cartridge_base: CartridgeBase,
     // selected_mode: Mode,
-    rtc_mode: bool,
-    ram_timer_enabled: bool,
-    reg_rtc: u8,
+    rtc: Option<Rtc>,
+    //Selected RTC register or None if a RAM bank is mapped
+    rtc_register: Option<u8>,
 }
 
 impl Mbc3 {
-    pub fn new(rom: Vec<u8>, ram_dumper: Option<Box<dyn RamDumper + Send>>) -> Self {
+    pub fn new(rom: Vec<u8>) -> Self {
         let cartridge_type = rom[CARTRIDGE_TYPE_ADDRESS];
         let has_ram = true;
-        let has_battery = cartridge_type == 0x13;
+        let has_battery = matches!(cartridge_type, 0x0F | 0x10 | 0x13);
+        let has_rtc = cartridge_type == 0x0F || cartridge_type == 0x10;
         let ram_size = get_ram_size(&rom);
 
-        let cartridge_base = CartridgeBase::new(rom, has_ram, ram_size, has_battery, ram_dumper);
+        let cartridge_base = CartridgeBase::new(rom, has_ram, ram_size, has_battery);
 
         Mbc3 {
             cartridge_base,
             // selected_mode: Mode::RomBankingMode,
-            rtc_mode: false,
-            ram_timer_enabled: false,
-            reg_rtc: 0,
+            rtc: if has_rtc { Some(Rtc::new()) } else { None },
+            rtc_register: None,
         }
     }
 }
@@ -45,9 +44,11 @@ impl Cartridge for Mbc3 {
             0x0..=0x1FFF => {
                 self.cartridge_base.ram_enabled = value == 0x0A;
             }
-            //Address range for rom bank number
+            //Address range for rom bank number. 0 is also 1
             0x2000..=0x3FFF => {
-                self.cartridge_base.rom_bank = value;
+                let bank_number = value & 0x7F;
+                self.cartridge_base.rom_bank =
+                    if bank_number == 0 { 1 } else { bank_number } as u16;
             }
             //Address range for RAM bank number
             // 0x4000..=0x5FFF => match self.selected_mode {
@@ -62,10 +63,10 @@ impl Cartridge for Mbc3 {
             // },
             0x4000..=0x5FFF => {
                 if value <= 0x03 {
-                    self.rtc_mode = false;
+                    self.rtc_register = None;
                     self.cartridge_base.ram_bank = value;
-                } else if (0x08..=0x0C).contains(&value) {
-                    self.rtc_mode = true;
+                } else if (RTC_SECONDS..=RTC_DAYS_HIGH).contains(&value) {
+                    self.rtc_register = Some(value);
                 }
             }
             //Select Mode
@@ -74,14 +75,10 @@ impl Cartridge for Mbc3 {
             //     1 => self.selected_mode = Mode::RamBankingMode,
             //     _ => {}
             // },
-            ERAM_REGION_START..=ERAM_REGION_END => {
-                if self.ram_timer_enabled {
-                    if self.rtc_mode {
-                        self.reg_rtc = value;
-                    } else {
-                        let offset = address - ERAM_REGION_START;
-                        self.write_ram(offset, value);
-                    }
+            //Latch clock data
+            0x6000..=0x7FFF => {
+                if let Some(ref mut rtc) = self.rtc {
+                    rtc.write_latch(value);
                 }
             }
             _ => {}
@@ -89,18 +86,51 @@ impl Cartridge for Mbc3 {
     }
 
     fn write_ram(&mut self, address: u16, value: u8) {
-        self.cartridge_base.write_ram(address, value);
+        match (self.rtc_register, &mut self.rtc) {
+            (Some(register), Some(rtc)) => {
+                if self.cartridge_base.ram_enabled {
+                    rtc.write(register, value);
+                    self.cartridge_base.dirty = true;
+                }
+            }
+            (Some(_), None) => {}
+            (None, _) => self.cartridge_base.write_ram(address, value),
+        }
     }
 
     fn read_ram(&self, address: u16) -> u8 {
-        self.cartridge_base.read_ram(address)
+        match (self.rtc_register, &self.rtc) {
+            (Some(register), Some(rtc)) if self.cartridge_base.ram_enabled => rtc.read(register),
+            (Some(_), _) => 0xFF,
+            (None, _) => self.cartridge_base.read_ram(address),
+        }
+    }
+
+    fn base(&self) -> &CartridgeBase {
+        &self.cartridge_base
     }
 
-    fn dump_savegame(&self) {
-        self.cartridge_base.dump_savegame();
+    fn base_mut(&mut self) -> &mut CartridgeBase {
+        &mut self.cartridge_base
     }
 
-    fn load_savegame(&mut self) {
-        self.cartridge_base.load_savegame();
+    fn extra_save_data(&self) -> Option<Vec<u8>> {
+        self.rtc.as_ref().map(|rtc| rtc.to_footer())
+    }
+
+    fn load_save_data(&mut self, data: &[u8]) -> Result<(), SaveDataError> {
+        let footer = self.cartridge_base.load_save_data(data)?;
+
+        if let Some(ref mut rtc) = self.rtc {
+            rtc.load_footer(footer);
+        }
+
+        Ok(())
+    }
+
+    fn tick(&mut self, clock_cycles: u32) {
+        if let Some(ref mut rtc) = self.rtc {
+            rtc.tick(clock_cycles);
+        }
     }
 }
@@ -1,24 +1,48 @@
 use crate::lib::cartridge::cartridge_base::CartridgeBase;
-use crate::lib::cartridge::{get_ram_size, Cartridge, RamDumper, CARTRIDGE_TYPE_ADDRESS};
+use crate::lib::cartridge::{get_ram_size, Cartridge, Rumble, CARTRIDGE_TYPE_ADDRESS};
 
 use super::regions::{
     RAM_BANK_SEL_END, RAM_BANK_SEL_START, RAM_ENABLE_END, ROM_BANK_SEL_END, ROM_BANK_SEL_START,
 };
 
+//On rumble cartridges bit 3 of the RAM bank register drives the motor
+const RUMBLE_MOTOR_BIT: u8 = 0x08;
+
 pub struct Mbc5 {
     cartridge_base: CartridgeBase,
+    has_rumble: bool,
+    rumble_active: bool,
+    rumble: Option<Box<dyn Rumble + Send>>,
 }
 
 impl Mbc5 {
-    pub fn new(rom: Vec<u8>, ram_dumper: Option<Box<dyn RamDumper + Send>>) -> Self {
+    pub fn new(rom: Vec<u8>, rumble: Option<Box<dyn Rumble + Send>>) -> Self {
         let cartridge_type = rom[CARTRIDGE_TYPE_ADDRESS];
-        let has_ram = cartridge_type == 0x02 || cartridge_type == 0x03;
-        let has_battery = cartridge_type == 0x1B;
+        let has_ram = matches!(cartridge_type, 0x1A | 0x1B | 0x1D | 0x1E);
+        let has_battery = cartridge_type == 0x1B || cartridge_type == 0x1E;
+        let has_rumble = (0x1C..=0x1E).contains(&cartridge_type);
         let ram_size = get_ram_size(&rom);
 
-        let cartridge_base = CartridgeBase::new(rom, has_ram, ram_size, has_battery, ram_dumper);
+        let cartridge_base = CartridgeBase::new(rom, has_ram, ram_size, has_battery);
+
+        Mbc5 {
+            cartridge_base,
+            has_rumble,
+            rumble_active: false,
+            rumble,
+        }
+    }
+
+    fn set_rumble(&mut self, active: bool) {
+        if self.rumble_active == active {
+            return;
+        }
+
+        self.rumble_active = active;
 
-        Mbc5 { cartridge_base }
+        if let Some(ref rumble) = self.rumble {
+            rumble.set_rumble(active);
+        }
     }
 }
 
@@ -34,14 +58,13 @@ impl Cartridge for Mbc5 {
             }
             ROM_BANK_SEL_START..=0x2FFF => {
                 // 8 least significant bits of ROM bank number
-                //0 is also 1
-                let bank_number = if value == 0 { 1 } else { value };
-                //Only set lower 8 bits
-                self.cartridge_base.rom_bank = bank_number & 0xFF;
+                //Unlike MBC1 bank 0 can be mapped
+                self.cartridge_base.rom_bank = self.cartridge_base.rom_bank & 0x100 | value as u16;
             }
             0x3000..=ROM_BANK_SEL_END => {
                 // 9th bit of ROM bank number
-                self.cartridge_base.rom_bank = self.cartridge_base.rom_bank & 0xFF | (value & 0x01);
+                self.cartridge_base.rom_bank =
+                    self.cartridge_base.rom_bank & 0xFF | ((value & 0x01) as u16) << 8;
             }
             RAM_BANK_SEL_START..=RAM_BANK_SEL_END => {
                 // RAM bank number
@@ -49,7 +72,12 @@ impl Cartridge for Mbc5 {
                 As for the MBC1s RAM Banking Mode, writing a value in the range $00-$0F maps
                 the corresponding external RAM bank (if any) into the memory area at A000-BFFF.
                 */
-                self.cartridge_base.ram_bank = value & 0x0F;
+                if self.has_rumble {
+                    self.cartridge_base.ram_bank = value & 0x07;
+                    self.set_rumble(value & RUMBLE_MOTOR_BIT == RUMBLE_MOTOR_BIT);
+                } else {
+                    self.cartridge_base.ram_bank = value & 0x0F;
+                }
             }
             _ => {}
         }
@@ -63,11 +91,11 @@ impl Cartridge for Mbc5 {
         self.cartridge_base.read_ram(address)
     }
 
-    fn dump_savegame(&self) {
-        self.cartridge_base.dump_savegame();
+    fn base(&self) -> &CartridgeBase {
+        &self.cartridge_base
     }
 
-    fn load_savegame(&mut self) {
-        self.cartridge_base.load_savegame();
+    fn base_mut(&mut self) -> &mut CartridgeBase {
+        &mut self.cartridge_base
     }
 }
@@ -0,0 +1,171 @@
+use crate::lib::cartridge::cartridge_base::CartridgeBase;
+use crate::lib::cartridge::flash::{Flash, FLASH_SIZE};
+use crate::lib::cartridge::{Cartridge, MappedBanks, SaveDataError, EXT_RAM_ADDRESS};
+
+//0x4000-0x7FFF and 0xA000-0xBFFF are split into two independently switchable windows
+const ROM_BANK_SIZE: usize = 0x2000;
+const RAM_BANK_SIZE: usize = 0x1000;
+const RAM_SIZE: usize = 32 * 1024;
+const RAM_BANK_MASK: u8 = 0x07;
+const ROM_BANK_MASK: u8 = 0x7F;
+//Value of the select register mapping the flash instead of the rom
+const SELECT_FLASH: u8 = 0x08;
+
+struct Window {
+    rom_bank: u8,
+    flash_selected: bool,
+    ram_bank: u8,
+}
+
+impl Window {
+    fn new() -> Window {
+        Window {
+            rom_bank: 0,
+            flash_selected: false,
+            ram_bank: 0,
+        }
+    }
+}
+
+/// MBC6 with 8 KiB rom or flash windows at 0x4000 and 0x6000 and 4 KiB RAM windows at 0xA000 and 0xB000.
+/// The flash is saved after the RAM
+pub struct Mbc6 {
+    cartridge_base: CartridgeBase,
+    windows: [Window; 2],
+    flash: Flash,
+    flash_enabled: bool,
+    flash_write_enabled: bool,
+}
+
+impl Mbc6 {
+    pub fn new(rom: Vec<u8>) -> Self {
+        let cartridge_base = CartridgeBase::new(rom, true, Some(RAM_SIZE), true);
+
+        Mbc6 {
+            cartridge_base,
+            windows: [Window::new(), Window::new()],
+            flash: Flash::new(),
+            flash_enabled: false,
+            flash_write_enabled: false,
+        }
+    }
+
+    fn flash_mapped(&self, window: usize) -> bool {
+        self.flash_enabled && self.windows[window].flash_selected
+    }
+}
+
+impl Cartridge for Mbc6 {
+    fn read(&self, address: u16) -> u8 {
+        match address {
+            0x0..=0x3FFF => self.cartridge_base.read(address),
+            _ => {
+                let window = (address as usize - 0x4000) / ROM_BANK_SIZE;
+                let bank = self.windows[window].rom_bank as usize;
+                let offset = address as usize % ROM_BANK_SIZE;
+
+                if self.flash_mapped(window) {
+                    self.flash.read(bank * ROM_BANK_SIZE + offset)
+                } else {
+                    self.cartridge_base
+                        .read_rom_bank(bank, ROM_BANK_SIZE, offset)
+                }
+            }
+        }
+    }
+
+    fn write(&mut self, address: u16, value: u8) {
+        match address {
+            0x0..=0x03FF => {
+                self.cartridge_base.ram_enabled = value & 0x0F == 0x0A;
+            }
+            //RAM bank numbers of both windows
+            0x0400..=0x07FF => self.windows[0].ram_bank = value & RAM_BANK_MASK,
+            0x0800..=0x0BFF => self.windows[1].ram_bank = value & RAM_BANK_MASK,
+            0x0C00..=0x0FFF => self.flash_enabled = value & 0x01 == 0x01,
+            0x1000 => self.flash_write_enabled = value & 0x01 == 0x01,
+            //Rom or flash bank numbers and selection of both windows
+            0x2000..=0x27FF => self.windows[0].rom_bank = value & ROM_BANK_MASK,
+            0x2800..=0x2FFF => self.windows[0].flash_selected = value == SELECT_FLASH,
+            0x3000..=0x37FF => self.windows[1].rom_bank = value & ROM_BANK_MASK,
+            0x3800..=0x3FFF => self.windows[1].flash_selected = value == SELECT_FLASH,
+            //Flash commands
+            0x4000..=0x7FFF => {
+                let window = (address as usize - 0x4000) / ROM_BANK_SIZE;
+                if self.flash_mapped(window) {
+                    let bank = self.windows[window].rom_bank as usize;
+                    let flash_address = bank * ROM_BANK_SIZE + address as usize % ROM_BANK_SIZE;
+                    self.flash
+                        .write(flash_address, value, self.flash_write_enabled);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn write_ram(&mut self, address: u16, value: u8) {
+        if !self.cartridge_base.ram_enabled {
+            return;
+        }
+
+        let window = (address as usize - EXT_RAM_ADDRESS) / RAM_BANK_SIZE;
+        let bank = self.windows[window].ram_bank as usize;
+        self.cartridge_base.write_ram_bank(
+            bank,
+            RAM_BANK_SIZE,
+            address as usize % RAM_BANK_SIZE,
+            value,
+        );
+    }
+
+    fn read_ram(&self, address: u16) -> u8 {
+        if !self.cartridge_base.ram_enabled {
+            return 0xFF;
+        }
+
+        let window = (address as usize - EXT_RAM_ADDRESS) / RAM_BANK_SIZE;
+        let bank = self.windows[window].ram_bank as usize;
+        self.cartridge_base
+            .read_ram_bank(bank, RAM_BANK_SIZE, address as usize % RAM_BANK_SIZE)
+    }
+
+    fn base(&self) -> &CartridgeBase {
+        &self.cartridge_base
+    }
+
+    fn base_mut(&mut self) -> &mut CartridgeBase {
+        &mut self.cartridge_base
+    }
+
+    /// Banks of the windows at 0x4000 and 0xA000
+    fn mapped_banks(&self) -> MappedBanks {
+        MappedBanks {
+            rom_bank_0: 0,
+            rom_bank: self.windows[0].rom_bank as u16,
+            ram_bank: self.windows[0].ram_bank,
+        }
+    }
+
+    fn is_dirty(&self) -> bool {
+        self.cartridge_base.is_dirty() || (self.cartridge_base.has_battery() && self.flash.dirty)
+    }
+
+    fn clear_dirty(&mut self) {
+        self.cartridge_base.dirty = false;
+        self.flash.dirty = false;
+    }
+
+    fn extra_save_data(&self) -> Option<Vec<u8>> {
+        Some(self.flash.data.clone())
+    }
+
+    /// Savegames without flash contents only restore the RAM
+    fn load_save_data(&mut self, data: &[u8]) -> Result<(), SaveDataError> {
+        let footer = self.cartridge_base.load_save_data(data)?;
+        if footer.len() == FLASH_SIZE {
+            self.flash.data = footer.to_vec();
+        }
+
+        Ok(())
+    }
+}
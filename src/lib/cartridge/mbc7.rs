@@ -0,0 +1,133 @@
+use crate::lib::cartridge::cartridge_base::CartridgeBase;
+use crate::lib::cartridge::eeprom::{Eeprom, EEPROM_SIZE};
+use crate::lib::cartridge::{Cartridge, TiltSensor};
+
+//Accelerometer value when the Game Boy lies flat and the change for a tilt of 1g
+const ACCELEROMETER_CENTER: u16 = 0x81D0;
+const ACCELEROMETER_RANGE: f32 = 0x70 as f32;
+//Value of both axes after the latch was erased
+const ACCELEROMETER_ERASED: u16 = 0x8000;
+
+pub struct Mbc7 {
+    cartridge_base: CartridgeBase,
+    //0xA000-0xAFFF needs both enables
+    ram_enabled_2: bool,
+    eeprom: Eeprom,
+    tilt_sensor: Option<Box<dyn TiltSensor + Send>>,
+    latch_ready: bool,
+    x_latch: u16,
+    y_latch: u16,
+}
+
+impl Mbc7 {
+    pub fn new(rom: Vec<u8>, tilt_sensor: Option<Box<dyn TiltSensor + Send>>) -> Self {
+        let mut cartridge_base = CartridgeBase::new(rom, true, Some(EEPROM_SIZE), true);
+
+        //An erased EEPROM contains only set bits
+        if let Some(ref mut ram) = cartridge_base.ram {
+            ram.fill(0xFF);
+        }
+
+        Mbc7 {
+            cartridge_base,
+            ram_enabled_2: false,
+            eeprom: Eeprom::new(),
+            tilt_sensor,
+            latch_ready: false,
+            x_latch: ACCELEROMETER_ERASED,
+            y_latch: ACCELEROMETER_ERASED,
+        }
+    }
+
+    fn registers_enabled(&self) -> bool {
+        self.cartridge_base.ram_enabled && self.ram_enabled_2
+    }
+
+    /// Tilting right increases X and tilting down increases Y
+    fn latch_accelerometer(&mut self) {
+        let (x, y) = self
+            .tilt_sensor
+            .as_ref()
+            .map_or((0.0, 0.0), |tilt_sensor| tilt_sensor.get_tilt());
+
+        self.x_latch = accelerometer_value(x);
+        self.y_latch = accelerometer_value(y);
+    }
+}
+
+fn accelerometer_value(tilt: f32) -> u16 {
+    (ACCELEROMETER_CENTER as f32 + tilt.clamp(-1.0, 1.0) * ACCELEROMETER_RANGE) as u16
+}
+
+impl Cartridge for Mbc7 {
+    fn read(&self, address: u16) -> u8 {
+        self.cartridge_base.read(address)
+    }
+
+    fn write(&mut self, address: u16, value: u8) {
+        match address {
+            0x0..=0x1FFF => {
+                self.cartridge_base.ram_enabled = value == 0x0A;
+            }
+            //Address range for rom bank number
+            0x2000..=0x3FFF => {
+                self.cartridge_base.rom_bank = (value & 0x7F) as u16;
+            }
+            0x4000..=0x5FFF => {
+                self.ram_enabled_2 = value == 0x40;
+            }
+            _ => {}
+        }
+    }
+
+    /// Registers are selected by bits 4-7 of the address
+    fn write_ram(&mut self, address: u16, value: u8) {
+        if !self.registers_enabled() || address > 0xAFFF {
+            return;
+        }
+
+        match (address >> 4) & 0x0F {
+            0x0 if value == 0x55 => {
+                self.latch_ready = true;
+                self.x_latch = ACCELEROMETER_ERASED;
+                self.y_latch = ACCELEROMETER_ERASED;
+            }
+            0x1 if value == 0xAA && self.latch_ready => {
+                self.latch_ready = false;
+                self.latch_accelerometer();
+            }
+            0x8 => {
+                if let Some(ref mut ram) = self.cartridge_base.ram {
+                    if self.eeprom.write(value, ram) {
+                        self.cartridge_base.dirty = true;
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn read_ram(&self, address: u16) -> u8 {
+        if !self.registers_enabled() || address > 0xAFFF {
+            return 0xFF;
+        }
+
+        match (address >> 4) & 0x0F {
+            0x2 => self.x_latch as u8,
+            0x3 => (self.x_latch >> 8) as u8,
+            0x4 => self.y_latch as u8,
+            0x5 => (self.y_latch >> 8) as u8,
+            0x6 => 0x00,
+            0x8 => self.eeprom.read(),
+            _ => 0xFF,
+        }
+    }
+
+    fn base(&self) -> &CartridgeBase {
+        &self.cartridge_base
+    }
+
+    fn base_mut(&mut self) -> &mut CartridgeBase {
+        &mut self.cartridge_base
+    }
+}
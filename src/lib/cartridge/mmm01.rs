@@ -0,0 +1,147 @@
+use crate::lib::cartridge::cartridge_base::CartridgeBase;
+use crate::lib::cartridge::header::{header_checksum, CartridgeHeader, HEADER_CHECKSUM_ADDRESS};
+use crate::lib::cartridge::{
+    get_ram_size, Cartridge, MappedBanks, CARTRIDGE_TYPE_ADDRESS, LOGO_ADDRESS, NINTENDO_LOGO,
+};
+
+const ROM_BANK_SIZE: usize = 0x4000;
+//The menu and its header are stored in the last 32 KiB
+pub const MENU_SIZE: usize = 0x8000;
+
+/// Mapper of MMM01 multicarts. After reset the menu in the last 32 KiB is mapped.
+/// The menu selects the first bank of a game and locks the mapper by writing to 0x0000-0x1FFF.
+/// Afterwards the game sees a normal MBC with banks relative to its first bank
+pub struct Mmm01 {
+    cartridge_base: CartridgeBase,
+    locked: bool,
+    //First bank of the selected game
+    bank0: u16,
+    menu_bank: u16,
+}
+
+impl Mmm01 {
+    pub fn new(rom: Vec<u8>) -> Self {
+        //Some dumps store the menu at the start of the rom instead
+        let menu_bank = if has_menu_at_end(&rom) {
+            (rom.len() / ROM_BANK_SIZE).saturating_sub(2) as u16
+        } else {
+            0
+        };
+        let menu_header = &rom[menu_bank as usize * ROM_BANK_SIZE..];
+        let cartridge_type = menu_header[CARTRIDGE_TYPE_ADDRESS];
+        let has_ram = cartridge_type == 0x0C || cartridge_type == 0x0D;
+        let has_battery = cartridge_type == 0x0D;
+        let ram_size = get_ram_size(menu_header);
+        let header = CartridgeHeader::parse(menu_header);
+
+        let mut cartridge_base = CartridgeBase::new(rom, has_ram, ram_size, has_battery);
+        //The header of the menu describes the whole cartridge
+        if let Ok(header) = header {
+            cartridge_base.header = header;
+        }
+
+        Mmm01 {
+            cartridge_base,
+            locked: false,
+            bank0: menu_bank,
+            menu_bank,
+        }
+    }
+
+    fn read_bank(&self, bank: u16, address: u16) -> u8 {
+        self.cartridge_base.read_rom_bank(
+            bank as usize,
+            ROM_BANK_SIZE,
+            address as usize & (ROM_BANK_SIZE - 1),
+        )
+    }
+}
+
+/// True if the last 32 KiB contain a valid header with a MMM01 cartridge type.
+/// The logo and header checksum keep game data of other mappers from looking like a menu
+pub fn has_menu_at_end(rom: &[u8]) -> bool {
+    if rom.len() <= MENU_SIZE || !rom.len().is_multiple_of(MENU_SIZE) {
+        return false;
+    }
+
+    let menu = &rom[rom.len() - MENU_SIZE..];
+    (0x0B..=0x0D).contains(&menu[CARTRIDGE_TYPE_ADDRESS])
+        && menu[LOGO_ADDRESS..LOGO_ADDRESS + NINTENDO_LOGO.len()] == NINTENDO_LOGO
+        && header_checksum(menu) == menu[HEADER_CHECKSUM_ADDRESS]
+}
+
+impl Cartridge for Mmm01 {
+    fn read(&self, address: u16) -> u8 {
+        match (self.locked, address) {
+            (false, 0x0..=0x3FFF) => self.read_bank(self.menu_bank, address),
+            (false, _) => self.read_bank(self.menu_bank + 1, address),
+            (true, 0x0..=0x3FFF) => self.read_bank(self.bank0, address),
+            (true, _) => self.read_bank(self.cartridge_base.rom_bank, address),
+        }
+    }
+
+    fn write(&mut self, address: u16, value: u8) {
+        if !self.locked {
+            match address {
+                0x0..=0x1FFF => {
+                    self.locked = true;
+                    self.cartridge_base.rom_bank = self.bank0 + 1;
+                }
+                //Lower 7 bits of the first bank of the game
+                0x2000..=0x3FFF => self.bank0 = self.bank0 & !0x7F | (value & 0x7F) as u16,
+                //Bits 4 and 5 are the upper 2 bits of the first bank
+                0x4000..=0x5FFF => self.bank0 = self.bank0 & !0x180 | ((value & 0x30) as u16) << 3,
+                _ => {}
+            }
+            return;
+        }
+
+        match address {
+            0x0..=0x1FFF => {
+                self.cartridge_base.ram_enabled = value & 0x0F == 0x0A;
+            }
+            //0 is also 1
+            0x2000..=0x3FFF => {
+                let bank_number = if value == 0 { 1 } else { value };
+                self.cartridge_base.rom_bank = self.bank0 + bank_number as u16;
+            }
+            0x4000..=0x5FFF => {
+                self.cartridge_base.ram_bank = value & 0x03;
+            }
+            _ => {}
+        }
+    }
+
+    fn write_ram(&mut self, address: u16, value: u8) {
+        self.cartridge_base.write_ram(address, value);
+    }
+
+    fn read_ram(&self, address: u16) -> u8 {
+        self.cartridge_base.read_ram(address)
+    }
+
+    fn base(&self) -> &CartridgeBase {
+        &self.cartridge_base
+    }
+
+    fn base_mut(&mut self) -> &mut CartridgeBase {
+        &mut self.cartridge_base
+    }
+
+    fn mapped_banks(&self) -> MappedBanks {
+        let banks = self.cartridge_base.mapped_banks();
+
+        if self.locked {
+            MappedBanks {
+                rom_bank_0: self.bank0,
+                ..banks
+            }
+        } else {
+            MappedBanks {
+                rom_bank_0: self.menu_bank,
+                rom_bank: self.menu_bank + 1,
+                ..banks
+            }
+        }
+    }
+}
@@ -1,21 +1,66 @@
+use crate::lib::cartridge::cartridge_base::CartridgeBase;
+use crate::lib::cartridge::detection::{detect_unlicensed_mapper, KnownRoms, UnlicensedMapper};
+use crate::lib::cartridge::header::{CartridgeHeader, HeaderError};
+use crate::lib::cartridge::huc1::HuC1;
+use crate::lib::cartridge::huc3::HuC3;
+use crate::lib::cartridge::m161::M161;
 use crate::lib::cartridge::mbc1::Mbc1;
 use crate::lib::cartridge::mbc2::Mbc2;
 use crate::lib::cartridge::mbc3::Mbc3;
 use crate::lib::cartridge::mbc5::Mbc5;
+use crate::lib::cartridge::mbc6::Mbc6;
+use crate::lib::cartridge::mbc7::Mbc7;
+use crate::lib::cartridge::mmm01::Mmm01;
+use crate::lib::cartridge::pocket_camera::PocketCamera;
 use crate::lib::cartridge::rom_only::RomOnlyCartridge;
+use crate::lib::cartridge::sachen::Sachen;
+use crate::lib::cartridge::wisdom_tree::WisdomTree;
+use std::{fmt, io};
 
 pub mod cartridge_base;
+pub mod detection;
+pub mod eeprom;
+pub mod flash;
+pub mod header;
+pub mod huc1;
+pub mod huc3;
+pub mod infrared;
+pub mod m161;
 pub mod mbc1;
 pub mod mbc2;
 pub mod mbc3;
 pub mod mbc5;
+pub mod mbc6;
+pub mod mbc7;
+pub mod mmm01;
+pub mod pocket_camera;
 pub mod regions;
 pub mod rom_only;
+pub mod rtc;
+pub mod sachen;
+pub mod wisdom_tree;
 
 pub const EXT_RAM_SIZE: usize = 8192;
 pub const EXT_RAM_ADDRESS: usize = 0xA000;
+pub const LOGO_ADDRESS: usize = 0x104;
+pub const TITLE_ADDRESS: usize = 0x134;
+pub const CGB_FLAG_ADDRESS: usize = 0x143;
+pub const NEW_LICENSEE_CODE_ADDRESS: usize = 0x144;
+pub const SGB_FLAG_ADDRESS: usize = 0x146;
+pub const OLD_LICENSEE_CODE_ADDRESS: usize = 0x14B;
 const CARTRIDGE_TYPE_ADDRESS: usize = 0x147;
 const RAM_SIZE_ADDRESS: usize = 0x149;
+//Two banks of 16 KiB are always mapped
+const MIN_ROM_SIZE: usize = 0x8000;
+//Largest rom the MBC5 can map
+pub const MAX_ROM_SIZE: usize = 0x800000;
+
+//Checked by the boot rom before starting a game
+pub const NINTENDO_LOGO: [u8; 48] = [
+    0xCE, 0xED, 0x66, 0x66, 0xCC, 0x0D, 0x00, 0x0B, 0x03, 0x73, 0x00, 0x83, 0x00, 0x0C, 0x00, 0x0D,
+    0x00, 0x08, 0x11, 0x1F, 0x88, 0x89, 0x00, 0x0E, 0xDC, 0xCC, 0x6E, 0xE6, 0xDD, 0xDD, 0xD9, 0x99,
+    0xBB, 0xBB, 0x67, 0x63, 0x6E, 0x0E, 0xEC, 0xCC, 0xDD, 0xDC, 0x99, 0x9F, 0xBB, 0xB9, 0x33, 0x3E,
+];
 
 /*
 Reference for Gameboy cartridge types:
@@ -31,6 +76,9 @@ Reference for Gameboy cartridge types:
   0x0B: ROM+MMM01
   0x0C: ROM+MMM01+SRAM
   0x0D: ROM+MMM01+SRAM+BATT
+  0x0F: ROM+MBC3+TIMER+BATT
+  0x10: ROM+MBC3+TIMER+RAM+BATT
+  0x11: ROM+MBC3
   0x12: ROM+MBC3+RAM
   0x13: ROM+MBC3+RAM+BATT
   0x19: ROM+MBC5
@@ -39,9 +87,12 @@ Reference for Gameboy cartridge types:
   0x1C: ROM+MBC5+RUMBLE
   0x1D: ROM+MBC5+RUMBLE+SRAM
   0x1E: ROM+MBC5+RUMBLE+SRAM+BATT
-  0x1F: Pocket Camera
+  0x20: ROM+MBC6+SRAM+FLASH+BATT
+  0x22: ROM+MBC7+SENSOR+RUMBLE+EEPROM+BATT
+  0xFC: Pocket Camera
   0xFD: Bandai TAMA5
   0xFE: Hudson HuC-3
+  0xFF: Hudson HuC-1
 */
 
 pub trait Cartridge {
@@ -49,31 +100,240 @@ pub trait Cartridge {
     fn write(&mut self, address: u16, value: u8);
     fn write_ram(&mut self, address: u16, value: u8);
     fn read_ram(&self, address: u16) -> u8;
-    fn dump_savegame(&self);
-    fn load_savegame(&mut self);
+    /// Rom, RAM and banks shared by all mappers
+    fn base(&self) -> &CartridgeBase;
+    fn base_mut(&mut self) -> &mut CartridgeBase;
+    fn header(&self) -> &CartridgeHeader {
+        &self.base().header
+    }
+    fn mapped_banks(&self) -> MappedBanks {
+        self.base().mapped_banks()
+    }
+    fn has_battery(&self) -> bool {
+        self.base().has_battery()
+    }
+    /// Whether the save data changed since the last clear_dirty
+    fn is_dirty(&self) -> bool {
+        self.base().is_dirty()
+    }
+    /// Marks the save data as written to disk
+    fn clear_dirty(&mut self) {
+        self.base_mut().dirty = false;
+    }
+    /// Battery backed RAM. None for cartridges without battery
+    fn save_data(&self) -> Option<&[u8]> {
+        self.base().save_data()
+    }
+    /// State saved after the RAM like the RTC
+    fn extra_save_data(&self) -> Option<Vec<u8>> {
+        None
+    }
+    /// Restores the RAM and the extra state from the data returned by savegame
+    fn load_save_data(&mut self, data: &[u8]) -> Result<(), SaveDataError> {
+        self.base_mut().load_save_data(data).map(|_| ())
+    }
+    /// Battery backed RAM followed by the extra state like in the savegames of other emulators
+    fn savegame(&self) -> Option<Vec<u8>> {
+        if !self.has_battery() {
+            return None;
+        }
+
+        let mut data = self.save_data().map(<[u8]>::to_vec).unwrap_or_default();
+        if let Some(extra_data) = self.extra_save_data() {
+            data.extend_from_slice(&extra_data);
+        }
+
+        (!data.is_empty()).then_some(data)
+    }
+    /// Advances components running on their own clock like the RTC
+    fn tick(&mut self, _clock_cycles: u32) {}
+}
+
+/// Banks currently mapped to 0x0000-0x3FFF, 0x4000-0x7FFF and 0xA000-0xBFFF
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct MappedBanks {
+    pub rom_bank_0: u16,
+    pub rom_bank: u16,
+    pub ram_bank: u8,
 }
 
+#[derive(Debug, PartialEq)]
+pub enum SaveDataError {
+    NoBattery,
+    //Savegames smaller than the RAM can not be loaded
+    TooShort { expected: usize, actual: usize },
+}
+
+impl fmt::Display for SaveDataError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SaveDataError::NoBattery => write!(f, "Cartridge has no battery to keep a savegame"),
+            SaveDataError::TooShort { expected, actual } => write!(
+                f,
+                "Savegame has {} bytes but the cartridge needs {} bytes",
+                actual, expected
+            ),
+        }
+    }
+}
+
+impl std::error::Error for SaveDataError {}
+
+/// Storage for savegames on top of the save data of the cartridge
 pub trait RamDumper {
-    fn dump(&self, data: &Vec<u8>);
+    fn dump(&self, data: &[u8]) -> io::Result<()>;
     fn load(&self) -> Option<Vec<u8>>;
+
+    /// Only battery backed cartridges are saved
+    fn save_cartridge(&self, cartridge: &dyn Cartridge) -> io::Result<()> {
+        match cartridge.savegame() {
+            Some(data) => self.dump(&data),
+            None => Ok(()),
+        }
+    }
+
+    /// A missing savegame is no error
+    fn load_cartridge(&self, cartridge: &mut dyn Cartridge) -> Result<(), SaveDataError> {
+        match self.load() {
+            Some(data) if cartridge.has_battery() => cartridge.load_save_data(&data),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Notified when a rumble cartridge turns its motor on or off
+pub trait Rumble {
+    fn set_rumble(&self, active: bool);
+}
+
+/// Input source for the accelerometer of MBC7 cartridges.
+/// Returns the tilt of the x and y axis from -1.0 to 1.0 with positive values to the right and down
+pub trait TiltSensor {
+    fn get_tilt(&self) -> (f32, f32);
+}
+
+/// Infrared port of HuC1 and HuC3 cartridges
+pub trait Infrared {
+    fn set_led(&self, _on: bool) {}
+    /// Without a partner there is no light to detect
+    fn is_light_detected(&self) -> bool {
+        false
+    }
+}
+
+/// Image source of the Game Boy Camera
+pub trait CameraSensor {
+    /// Returns 128x112 grayscale pixels row by row with 0 as black
+    fn capture(&mut self) -> Vec<u8>;
+}
+
+/// Hardware of special cartridges connected to the frontend
+#[derive(Default)]
+pub struct Peripherals {
+    pub rumble: Option<Box<dyn Rumble + Send>>,
+    pub tilt_sensor: Option<Box<dyn TiltSensor + Send>>,
+    pub infrared: Option<Box<dyn Infrared + Send>>,
+    pub camera: Option<Box<dyn CameraSensor + Send>>,
+}
+
+impl Peripherals {
+    pub fn new() -> Peripherals {
+        Peripherals::default()
+    }
 }
 
+/// How errors in the header are handled when loading a rom
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum HeaderValidation {
+    //Header errors except a wrong global checksum prevent loading
+    Strict,
+    //Header errors are ignored and missing rom banks are filled with 0xFF
+    WarnOnly,
+}
+
+#[derive(Debug)]
+pub enum CartridgeError {
+    InvalidHeader(HeaderError),
+    UnknownCartridgeType(u8),
+}
+
+impl fmt::Display for CartridgeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CartridgeError::InvalidHeader(error) => write!(f, "Invalid header: {}", error),
+            CartridgeError::UnknownCartridgeType(cartridge_type) => {
+                write!(f, "Unknown cartridge type: 0x{:X}", cartridge_type)
+            }
+        }
+    }
+}
+
+impl std::error::Error for CartridgeError {}
+
 pub fn new_cartridge(
-    rom: Vec<u8>,
-    ram_dumper: Option<Box<dyn RamDumper + Send>>,
-) -> Result<Box<dyn Cartridge + Send>, String> {
-    let cartridge_type = rom[CARTRIDGE_TYPE_ADDRESS];
+    mut rom: Vec<u8>,
+    peripherals: Peripherals,
+    validation: HeaderValidation,
+    known_roms: &KnownRoms,
+) -> Result<Box<dyn Cartridge + Send>, CartridgeError> {
+    let header = CartridgeHeader::parse(&rom).map_err(CartridgeError::InvalidHeader)?;
+
+    //The header of unlicensed games and multicarts can not be trusted
+    if let Some(mapper) = detect_unlicensed_mapper(&rom, known_roms) {
+        return Ok(match mapper {
+            UnlicensedMapper::Mmm01 => Box::new(Mmm01::new(rom)),
+            UnlicensedMapper::WisdomTree => Box::new(WisdomTree::new(rom)),
+            UnlicensedMapper::Sachen => Box::new(Sachen::new(rom)),
+            UnlicensedMapper::M161 => Box::new(M161::new(rom)),
+        });
+    }
+
+    match validation {
+        HeaderValidation::Strict => {
+            //The global checksum is not checked by the hardware and wrong in many homebrew roms
+            let error = header
+                .validate(&rom)
+                .into_iter()
+                .find(|error| !matches!(error, HeaderError::GlobalChecksumMismatch { .. }));
+
+            if let Some(error) = error {
+                return Err(CartridgeError::InvalidHeader(error));
+            }
+        }
+        HeaderValidation::WarnOnly => {
+            let rom_size = header.rom_size.unwrap_or(0).max(MIN_ROM_SIZE);
+            if rom.len() < rom_size {
+                rom.resize(rom_size, 0xFF);
+            }
+        }
+    }
+
+    let cartridge_type = header.cartridge_type;
     match cartridge_type {
-        0x00 | 0x08..=0x09 => Ok(Box::new(RomOnlyCartridge::new(rom, ram_dumper))),
-        0x01..=0x03 => Ok(Box::new(Mbc1::new(rom, ram_dumper))),
-        0x05..=0x06 => Ok(Box::new(Mbc2::new(rom, ram_dumper))),
-        0x12..=0x13 => Ok(Box::new(Mbc3::new(rom, ram_dumper))),
-        0x19 | 0x1A..=0x1E => Ok(Box::new(Mbc5::new(rom, ram_dumper))),
-        _ => Err(format!("Unknown cartridge type: 0x{:X}", cartridge_type)),
+        0x00 | 0x08..=0x09 => Ok(Box::new(RomOnlyCartridge::new(rom))),
+        0x01..=0x03 => Ok(Box::new(Mbc1::new(rom))),
+        0x05..=0x06 => Ok(Box::new(Mbc2::new(rom))),
+        0x0F..=0x13 => Ok(Box::new(Mbc3::new(rom))),
+        0x19..=0x1E => Ok(Box::new(Mbc5::new(rom, peripherals.rumble))),
+        0x20 => Ok(Box::new(Mbc6::new(rom))),
+        0x22 => Ok(Box::new(Mbc7::new(rom, peripherals.tilt_sensor))),
+        0xFC => Ok(Box::new(PocketCamera::new(rom, peripherals.camera))),
+        0xFE => Ok(Box::new(HuC3::new(rom, peripherals.infrared))),
+        0xFF => Ok(Box::new(HuC1::new(rom, peripherals.infrared))),
+        _ => Err(CartridgeError::UnknownCartridgeType(cartridge_type)),
     }
 }
 
-pub fn get_ram_size(rom: &Vec<u8>) -> Option<usize> {
+/// The title is padded with zeros and shortened to 15 characters by the CGB flag
+pub fn get_title(rom: &[u8]) -> String {
+    rom[TITLE_ADDRESS..CGB_FLAG_ADDRESS]
+        .iter()
+        .take_while(|&&character| character != 0)
+        .map(|&character| character as char)
+        .collect()
+}
+
+pub fn get_ram_size(rom: &[u8]) -> Option<usize> {
     match rom[RAM_SIZE_ADDRESS] {
         0x00 => None,
         0x01 => Some(2 * 1024),
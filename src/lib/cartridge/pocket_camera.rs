@@ -0,0 +1,212 @@
+use crate::lib::cartridge::cartridge_base::CartridgeBase;
+use crate::lib::cartridge::{CameraSensor, Cartridge, EXT_RAM_ADDRESS};
+
+pub const SENSOR_WIDTH: usize = 128;
+pub const SENSOR_HEIGHT: usize = 112;
+const RAM_SIZE: usize = 128 * 1024;
+//The captured image is stored as 16x14 tiles in RAM bank 0
+const IMAGE_OFFSET: usize = 0x100;
+const TILES_PER_ROW: usize = SENSOR_WIDTH / 8;
+
+const REGISTERS: usize = 0x36;
+//Bit 0 of register 0 starts a capture and stays set while capturing
+const REG_CONTROL: usize = 0x00;
+//Bit 7 disables the negative offset, bits 5-6 select the edge mode
+const REG_EDGE_MODE: usize = 0x01;
+const REG_EXPOSURE_HIGH: usize = 0x02;
+const REG_EXPOSURE_LOW: usize = 0x03;
+//Bits 4-6 select the edge enhancement ratio, bit 3 inverts the image
+const REG_EDGE_RATIO_INVERT: usize = 0x04;
+//4x4 matrix with 3 thresholds each
+const REG_DITHER_MATRIX: usize = 0x06;
+
+const EDGE_RATIOS: [f32; 8] = [0.5, 0.75, 1.0, 1.25, 2.0, 3.0, 4.0, 5.0];
+//Exposure at which the sensor output matches the input image
+const NEUTRAL_EXPOSURE: f32 = 0x1000 as f32;
+//Sensor output when no image source is connected
+const GRAY: u8 = 0x80;
+
+/// Game Boy Camera with a M64282FP sensor. Writing to 0x4000-0x5FFF with bit 4 set maps the sensor
+/// registers to 0xA000-0xBFFF instead of the RAM
+pub struct PocketCamera {
+    cartridge_base: CartridgeBase,
+    registers: [u8; REGISTERS],
+    registers_mapped: bool,
+    capture_cycles_left: u32,
+    sensor: Option<Box<dyn CameraSensor + Send>>,
+}
+
+impl PocketCamera {
+    pub fn new(rom: Vec<u8>, sensor: Option<Box<dyn CameraSensor + Send>>) -> Self {
+        let cartridge_base = CartridgeBase::new(rom, true, Some(RAM_SIZE), true);
+
+        PocketCamera {
+            cartridge_base,
+            registers: [0; REGISTERS],
+            registers_mapped: false,
+            capture_cycles_left: 0,
+            sensor,
+        }
+    }
+
+    fn exposure(&self) -> u16 {
+        (self.registers[REG_EXPOSURE_HIGH] as u16) << 8 | self.registers[REG_EXPOSURE_LOW] as u16
+    }
+
+    /// Clock cycles until the capture is finished. Disabling the negative offset saves 512 cycles
+    fn capture_cycles(&self) -> u32 {
+        let offset_cycles = if self.registers[REG_EDGE_MODE] & 0x80 == 0x80 {
+            0
+        } else {
+            512
+        };
+
+        32446 + offset_cycles + 16 * self.exposure() as u32
+    }
+
+    fn write_register(&mut self, register: usize, value: u8) {
+        if register >= REGISTERS {
+            return;
+        }
+
+        if register == REG_CONTROL {
+            let capturing = self.capture_cycles_left > 0;
+            self.registers[REG_CONTROL] = value & 0x07;
+
+            if value & 0x01 == 0x01 && !capturing {
+                self.capture_cycles_left = self.capture_cycles();
+            } else if value & 0x01 == 0 {
+                self.capture_cycles_left = 0;
+            }
+        } else {
+            self.registers[register] = value;
+        }
+    }
+
+    /// Applies the exposure and edge enhancement to the sensor image and dithers it to 2bpp tiles
+    fn capture(&mut self) {
+        let image = match self.sensor {
+            Some(ref mut sensor) => sensor.capture(),
+            None => vec![GRAY; SENSOR_WIDTH * SENSOR_HEIGHT],
+        };
+
+        let exposure = self.exposure() as f32 / NEUTRAL_EXPOSURE;
+        let brightness = |x: isize, y: isize| -> f32 {
+            let x = x.clamp(0, SENSOR_WIDTH as isize - 1) as usize;
+            let y = y.clamp(0, SENSOR_HEIGHT as isize - 1) as usize;
+            image.get(y * SENSOR_WIDTH + x).copied().unwrap_or(GRAY) as f32 * exposure
+        };
+
+        let edge_enhancement = self.registers[REG_EDGE_MODE] & 0xE0 == 0xE0;
+        let edge_ratio =
+            EDGE_RATIOS[((self.registers[REG_EDGE_RATIO_INVERT] >> 4) & 0x07) as usize];
+        let invert = self.registers[REG_EDGE_RATIO_INVERT] & 0x08 == 0x08;
+
+        let ram = match self.cartridge_base.ram {
+            Some(ref mut ram) => ram,
+            None => return,
+        };
+
+        for y in 0..SENSOR_HEIGHT {
+            for x in 0..SENSOR_WIDTH {
+                let (x_pos, y_pos) = (x as isize, y as isize);
+                let mut color = brightness(x_pos, y_pos);
+
+                if edge_enhancement {
+                    let neighbours = brightness(x_pos - 1, y_pos)
+                        + brightness(x_pos + 1, y_pos)
+                        + brightness(x_pos, y_pos - 1)
+                        + brightness(x_pos, y_pos + 1);
+                    color += (color * 4.0 - neighbours) * edge_ratio;
+                }
+
+                let mut color = color.clamp(0.0, 255.0) as u8;
+                if invert {
+                    color = 255 - color;
+                }
+
+                let matrix_offset = REG_DITHER_MATRIX + ((x & 3) + (y & 3) * 4) * 3;
+                let thresholds = &self.registers[matrix_offset..matrix_offset + 3];
+                let shade = thresholds
+                    .iter()
+                    .position(|&threshold| color < threshold)
+                    .map_or(0, |position| 3 - position as u8);
+
+                let tile = (y / 8) * TILES_PER_ROW + x / 8;
+                let offset = IMAGE_OFFSET + tile * 16 + (y % 8) * 2;
+                let bit = 7 - (x % 8);
+                ram[offset] = ram[offset] & !(1 << bit) | (shade & 0x01) << bit;
+                ram[offset + 1] = ram[offset + 1] & !(1 << bit) | (shade >> 1) << bit;
+            }
+        }
+    }
+}
+
+impl Cartridge for PocketCamera {
+    fn read(&self, address: u16) -> u8 {
+        self.cartridge_base.read(address)
+    }
+
+    fn write(&mut self, address: u16, value: u8) {
+        match address {
+            0x0..=0x1FFF => {
+                self.cartridge_base.ram_enabled = value & 0x0F == 0x0A;
+            }
+            //Address range for rom bank number
+            0x2000..=0x3FFF => {
+                self.cartridge_base.rom_bank = (value & 0x3F) as u16;
+            }
+            0x4000..=0x5FFF => {
+                self.registers_mapped = value & 0x10 == 0x10;
+                if !self.registers_mapped {
+                    self.cartridge_base.ram_bank = value & 0x0F;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn write_ram(&mut self, address: u16, value: u8) {
+        if self.registers_mapped {
+            self.write_register((address as usize - EXT_RAM_ADDRESS) & 0x7F, value);
+        } else {
+            self.cartridge_base.write_ram(address, value);
+        }
+    }
+
+    /// Only the control register can be read back
+    fn read_ram(&self, address: u16) -> u8 {
+        if !self.registers_mapped {
+            return self.cartridge_base.read_ram(address);
+        }
+
+        match (address as usize - EXT_RAM_ADDRESS) & 0x7F {
+            REG_CONTROL => {
+                self.registers[REG_CONTROL] & !0x01 | (self.capture_cycles_left > 0) as u8
+            }
+            _ => 0x00,
+        }
+    }
+
+    fn base(&self) -> &CartridgeBase {
+        &self.cartridge_base
+    }
+
+    fn base_mut(&mut self) -> &mut CartridgeBase {
+        &mut self.cartridge_base
+    }
+
+    fn tick(&mut self, clock_cycles: u32) {
+        if self.capture_cycles_left == 0 {
+            return;
+        }
+
+        self.capture_cycles_left = self.capture_cycles_left.saturating_sub(clock_cycles);
+
+        if self.capture_cycles_left == 0 {
+            self.capture();
+            self.cartridge_base.dirty = true;
+            self.registers[REG_CONTROL] &= !0x01;
+        }
+    }
+}
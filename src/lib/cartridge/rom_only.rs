@@ -1,18 +1,18 @@
 use crate::lib::cartridge::cartridge_base::CartridgeBase;
-use crate::lib::cartridge::{get_ram_size, Cartridge, RamDumper, CARTRIDGE_TYPE_ADDRESS};
+use crate::lib::cartridge::{get_ram_size, Cartridge, CARTRIDGE_TYPE_ADDRESS};
 
 pub struct RomOnlyCartridge {
     cartridge_base: CartridgeBase,
 }
 
 impl RomOnlyCartridge {
-    pub fn new(rom: Vec<u8>, ram_dumper: Option<Box<dyn RamDumper + Send>>) -> Self {
+    pub fn new(rom: Vec<u8>) -> Self {
         let cartridge_type = rom[CARTRIDGE_TYPE_ADDRESS];
         let has_ram = cartridge_type == 0x08 || cartridge_type == 0x09;
         let has_battery = cartridge_type == 0x09;
         let ram_size = get_ram_size(&rom);
 
-        let cartridge_base = CartridgeBase::new(rom, has_ram, ram_size, has_battery, ram_dumper);
+        let cartridge_base = CartridgeBase::new(rom, has_ram, ram_size, has_battery);
 
         RomOnlyCartridge { cartridge_base }
     }
@@ -36,11 +36,11 @@ impl Cartridge for RomOnlyCartridge {
         self.cartridge_base.read_ram(address)
     }
 
-    fn dump_savegame(&self) {
-        self.cartridge_base.dump_savegame();
+    fn base(&self) -> &CartridgeBase {
+        &self.cartridge_base
     }
 
-    fn load_savegame(&mut self) {
-        self.cartridge_base.load_savegame()
+    fn base_mut(&mut self) -> &mut CartridgeBase {
+        &mut self.cartridge_base
     }
 }
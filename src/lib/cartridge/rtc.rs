@@ -0,0 +1,251 @@
+use crate::lib::emulation::CPU_CLOCK_HZ;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+//Register numbers selected by writing to 0x4000-0x5FFF
+pub const RTC_SECONDS: u8 = 0x08;
+pub const RTC_MINUTES: u8 = 0x09;
+pub const RTC_HOURS: u8 = 0x0A;
+pub const RTC_DAYS_LOW: u8 = 0x0B;
+pub const RTC_DAYS_HIGH: u8 = 0x0C;
+
+//Bits of the DH register
+const DAY_HIGH_BIT: u8 = 0x01;
+const HALT_FLAG: u8 = 0x40;
+const DAY_CARRY_FLAG: u8 = 0x80;
+
+const MAX_DAYS: u64 = 0x1FF;
+
+//Footer appended to the savegame: current and latched registers as 32 bit values and the UNIX timestamp
+pub const RTC_FOOTER_SIZE: usize = 48;
+//Older saves store the timestamp with 32 bit only
+const RTC_FOOTER_SIZE_SHORT_TIMESTAMP: usize = 44;
+const RTC_REGISTERS: usize = 5;
+
+#[derive(Clone, Copy)]
+struct RtcRegisters {
+    seconds: u8,
+    minutes: u8,
+    hours: u8,
+    days_low: u8,
+    days_high: u8,
+}
+
+impl RtcRegisters {
+    fn new() -> RtcRegisters {
+        RtcRegisters {
+            seconds: 0,
+            minutes: 0,
+            hours: 0,
+            days_low: 0,
+            days_high: 0,
+        }
+    }
+
+    fn read(&self, register: u8) -> u8 {
+        match register {
+            RTC_SECONDS => self.seconds,
+            RTC_MINUTES => self.minutes,
+            RTC_HOURS => self.hours,
+            RTC_DAYS_LOW => self.days_low,
+            RTC_DAYS_HIGH => self.days_high,
+            _ => 0xFF,
+        }
+    }
+
+    fn write(&mut self, register: u8, value: u8) {
+        match register {
+            RTC_SECONDS => self.seconds = value & 0x3F,
+            RTC_MINUTES => self.minutes = value & 0x3F,
+            RTC_HOURS => self.hours = value & 0x1F,
+            RTC_DAYS_LOW => self.days_low = value,
+            RTC_DAYS_HIGH => self.days_high = value & (DAY_HIGH_BIT | HALT_FLAG | DAY_CARRY_FLAG),
+            _ => {}
+        }
+    }
+
+    fn to_array(self) -> [u8; RTC_REGISTERS] {
+        [
+            self.seconds,
+            self.minutes,
+            self.hours,
+            self.days_low,
+            self.days_high,
+        ]
+    }
+
+    fn days(&self) -> u64 {
+        ((self.days_high & DAY_HIGH_BIT) as u64) << 8 | self.days_low as u64
+    }
+
+    /// The day counter has 9 bits. The carry flag stays set until it is cleared by the game
+    fn set_days(&mut self, days: u64) {
+        if days > MAX_DAYS {
+            self.days_high |= DAY_CARRY_FLAG;
+        }
+
+        let days = days & MAX_DAYS;
+        self.days_low = days as u8;
+        self.days_high = self.days_high & !DAY_HIGH_BIT | (days >> 8) as u8;
+    }
+
+    fn is_in_range(&self) -> bool {
+        self.seconds < 60 && self.minutes < 60 && self.hours < 24
+    }
+
+    /// Out of range values count up to the register size and wrap to 0 without a carry
+    fn tick_second(&mut self) {
+        self.seconds = (self.seconds + 1) & 0x3F;
+        if self.seconds != 60 {
+            return;
+        }
+
+        self.seconds = 0;
+        self.minutes = (self.minutes + 1) & 0x3F;
+        if self.minutes != 60 {
+            return;
+        }
+
+        self.minutes = 0;
+        self.hours = (self.hours + 1) & 0x1F;
+        if self.hours != 24 {
+            return;
+        }
+
+        self.hours = 0;
+        self.set_days(self.days() + 1);
+    }
+
+    fn advance(&mut self, mut seconds: u64) {
+        //Step through out of range values until the registers hold a valid time again
+        while seconds > 0 && !self.is_in_range() {
+            self.tick_second();
+            seconds -= 1;
+        }
+
+        let total = self.seconds as u64 + seconds;
+        self.seconds = (total % 60) as u8;
+        let total = self.minutes as u64 + total / 60;
+        self.minutes = (total % 60) as u8;
+        let total = self.hours as u64 + total / 60;
+        self.hours = (total % 24) as u8;
+        self.set_days(self.days() + total / 24);
+    }
+}
+
+/// Real time clock of the MBC3. Time advances with the emulated clock cycles while running
+/// and with the host time while the emulator is closed
+pub struct Rtc {
+    registers: RtcRegisters,
+    latched_registers: RtcRegisters,
+    //Clock cycles since the last second
+    cycles: usize,
+    //Latching needs a write of 0 followed by a write of 1
+    latch_prepared: bool,
+}
+
+impl Default for Rtc {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Rtc {
+    pub fn new() -> Rtc {
+        Rtc {
+            registers: RtcRegisters::new(),
+            latched_registers: RtcRegisters::new(),
+            cycles: 0,
+            latch_prepared: false,
+        }
+    }
+
+    pub fn tick(&mut self, clock_cycles: u32) {
+        if self.is_halted() {
+            return;
+        }
+
+        self.cycles += clock_cycles as usize;
+
+        while self.cycles >= CPU_CLOCK_HZ {
+            self.cycles -= CPU_CLOCK_HZ;
+            self.registers.tick_second();
+        }
+    }
+
+    /// Games read the registers copied by the last latch
+    pub fn read(&self, register: u8) -> u8 {
+        self.latched_registers.read(register)
+    }
+
+    pub fn write(&mut self, register: u8, value: u8) {
+        //Writing the seconds resets the counter for the next second
+        if register == RTC_SECONDS {
+            self.cycles = 0;
+        }
+
+        self.registers.write(register, value);
+    }
+
+    pub fn write_latch(&mut self, value: u8) {
+        if self.latch_prepared && value == 0x01 {
+            self.latched_registers = self.registers;
+        }
+
+        self.latch_prepared = value == 0x00;
+    }
+
+    fn is_halted(&self) -> bool {
+        self.registers.days_high & HALT_FLAG == HALT_FLAG
+    }
+
+    /// Creates the footer stored after the cartridge RAM in the savegame
+    pub fn to_footer(&self) -> Vec<u8> {
+        let mut footer = Vec::with_capacity(RTC_FOOTER_SIZE);
+
+        for register in self
+            .registers
+            .to_array()
+            .iter()
+            .chain(self.latched_registers.to_array().iter())
+        {
+            footer.extend_from_slice(&(*register as u32).to_le_bytes());
+        }
+
+        footer.extend_from_slice(&current_timestamp().to_le_bytes());
+        footer
+    }
+
+    /// Restores the registers from a savegame footer and catches up with the time passed since saving
+    pub fn load_footer(&mut self, footer: &[u8]) {
+        if footer.len() != RTC_FOOTER_SIZE && footer.len() != RTC_FOOTER_SIZE_SHORT_TIMESTAMP {
+            return;
+        }
+
+        let values: Vec<u8> = footer[..RTC_REGISTERS * 2 * 4]
+            .chunks(4)
+            .map(|chunk| chunk[0])
+            .collect();
+
+        for (index, register) in (RTC_SECONDS..=RTC_DAYS_HIGH).enumerate() {
+            self.registers.write(register, values[index]);
+            self.latched_registers
+                .write(register, values[RTC_REGISTERS + index]);
+        }
+
+        let timestamp_bytes = &footer[RTC_REGISTERS * 2 * 4..];
+        let mut timestamp = [0; 8];
+        timestamp[..timestamp_bytes.len()].copy_from_slice(timestamp_bytes);
+        let timestamp = u64::from_le_bytes(timestamp);
+
+        if !self.is_halted() {
+            self.registers
+                .advance(current_timestamp().saturating_sub(timestamp));
+        }
+    }
+}
+
+fn current_timestamp() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_secs())
+}
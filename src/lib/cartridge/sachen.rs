@@ -0,0 +1,106 @@
+use crate::lib::cartridge::cartridge_base::mirrored_index;
+use crate::lib::cartridge::cartridge_base::CartridgeBase;
+use crate::lib::cartridge::{Cartridge, MappedBanks};
+
+const ROM_BANK_SIZE: usize = 0x4000;
+
+/// Unlicensed mapper of Sachen MMC1 and MMC2 games.
+/// Both only differ in how the scrambled logo is unlocked for the boot rom. Without a boot rom
+/// the mapper starts unlocked, so the header page is not scrambled
+pub struct Sachen {
+    cartridge_base: CartridgeBase,
+    //Banks are combined from the base bank and the selected bank by the mask
+    base_bank: u8,
+    unmasked_bank: u8,
+    mask: u8,
+}
+
+impl Sachen {
+    pub fn new(rom: Vec<u8>) -> Self {
+        Sachen {
+            cartridge_base: CartridgeBase::new(rom, false, None, false),
+            base_bank: 0,
+            unmasked_bank: 1,
+            mask: 0,
+        }
+    }
+
+    /// Base bank and mask can only be changed while bits 4 and 5 of the selected bank are set
+    fn is_base_writable(&self) -> bool {
+        self.unmasked_bank & 0x30 == 0x30
+    }
+
+    fn read_bank(&self, bank: u8, address: u16) -> u8 {
+        let index = mirrored_index(
+            self.cartridge_base.rom.len(),
+            bank as usize,
+            ROM_BANK_SIZE,
+            address as usize & (ROM_BANK_SIZE - 1),
+        );
+        index.map_or(0xFF, |index| self.cartridge_base.rom[index])
+    }
+}
+
+/// Swaps address lines A0 with A6 and A1 with A4 in the header page like the locked mapper.
+/// Only used to find the Nintendo logo for the boot rom during detection
+pub fn unscramble_address(address: u16) -> u16 {
+    if address & 0xFF00 != 0x0100 {
+        return address;
+    }
+
+    address & 0xFFAC
+        | (address & 0x40) >> 6
+        | (address & 0x10) >> 3
+        | (address & 0x02) << 3
+        | (address & 0x01) << 6
+}
+
+impl Cartridge for Sachen {
+    fn read(&self, address: u16) -> u8 {
+        match address {
+            0x0..=0x3FFF => self.read_bank(self.base_bank & self.mask, address),
+            _ => {
+                let bank = self.unmasked_bank & !self.mask | self.base_bank & self.mask;
+                self.read_bank(bank, address)
+            }
+        }
+    }
+
+    fn write(&mut self, address: u16, value: u8) {
+        match address {
+            0x0..=0x1FFF if self.is_base_writable() => {
+                self.base_bank = value;
+            }
+            //0 is also 1
+            0x2000..=0x3FFF => {
+                self.unmasked_bank = if value == 0 { 1 } else { value };
+            }
+            0x4000..=0x5FFF if self.is_base_writable() => {
+                self.mask = value;
+            }
+            _ => {}
+        }
+    }
+
+    fn write_ram(&mut self, _address: u16, _value: u8) {}
+
+    fn read_ram(&self, _address: u16) -> u8 {
+        0xFF
+    }
+
+    fn base(&self) -> &CartridgeBase {
+        &self.cartridge_base
+    }
+
+    fn base_mut(&mut self) -> &mut CartridgeBase {
+        &mut self.cartridge_base
+    }
+
+    fn mapped_banks(&self) -> MappedBanks {
+        MappedBanks {
+            rom_bank_0: (self.base_bank & self.mask) as u16,
+            rom_bank: (self.unmasked_bank & !self.mask | self.base_bank & self.mask) as u16,
+            ram_bank: 0,
+        }
+    }
+}
@@ -0,0 +1,55 @@
+use crate::lib::cartridge::cartridge_base::CartridgeBase;
+use crate::lib::cartridge::{Cartridge, MappedBanks};
+
+const ROM_BANK_SIZE: usize = 0x8000;
+
+/// Unlicensed mapper of Wisdom Tree games. The lower byte of the address written to 0x0000-0x3FFF
+/// selects a 32 KiB bank for the whole rom area. There is no RAM
+pub struct WisdomTree {
+    cartridge_base: CartridgeBase,
+    rom_bank: usize,
+}
+
+impl WisdomTree {
+    pub fn new(rom: Vec<u8>) -> Self {
+        WisdomTree {
+            cartridge_base: CartridgeBase::new(rom, false, None, false),
+            rom_bank: 0,
+        }
+    }
+}
+
+impl Cartridge for WisdomTree {
+    fn read(&self, address: u16) -> u8 {
+        self.cartridge_base
+            .read_rom_bank(self.rom_bank, ROM_BANK_SIZE, address as usize)
+    }
+
+    fn write(&mut self, address: u16, _value: u8) {
+        if address <= 0x3FFF {
+            self.rom_bank = (address & 0xFF) as usize;
+        }
+    }
+
+    fn write_ram(&mut self, _address: u16, _value: u8) {}
+
+    fn read_ram(&self, _address: u16) -> u8 {
+        0xFF
+    }
+
+    fn base(&self) -> &CartridgeBase {
+        &self.cartridge_base
+    }
+
+    fn base_mut(&mut self) -> &mut CartridgeBase {
+        &mut self.cartridge_base
+    }
+
+    fn mapped_banks(&self) -> MappedBanks {
+        MappedBanks {
+            rom_bank_0: (self.rom_bank * 2) as u16,
+            rom_bank: (self.rom_bank * 2 + 1) as u16,
+            ram_bank: 0,
+        }
+    }
+}
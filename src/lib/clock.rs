@@ -20,8 +20,16 @@ impl Clock {
         }
     }
 
-    pub fn cycle(&mut self, clock_cycles: u8) {
-        self.clock_cycles_passed_frame += clock_cycles as usize;
+    /// Frames are paced in normal speed clock cycles. In double speed mode
+    /// the cpu executes twice as many clock cycles per frame
+    pub fn cycle(&mut self, clock_cycles: u32, double_speed: bool) {
+        let normal_speed_cycles = if double_speed {
+            clock_cycles / 2
+        } else {
+            clock_cycles
+        };
+
+        self.clock_cycles_passed_frame += normal_speed_cycles as usize;
         self.machine_cycles_passed_frame += (clock_cycles / 4) as usize;
     }
 
@@ -2,13 +2,13 @@ use crate::lib::cpu::instructions;
 use crate::lib::cpu::instructions::{ExecutionType, Instruction};
 use crate::lib::cpu::interrupt_handler::handle_interrupts;
 use crate::lib::cpu::registers::Registers;
+use crate::lib::emulation::HardwareMode;
 use crate::lib::memory::interrupts::Interrupt;
 use crate::lib::memory::mmu::{Mmu, Opcode};
 
 pub enum InterruptAction {
     None,
     Enable,
-    Disable,
 }
 
 pub struct Cpu {
@@ -16,23 +16,70 @@ pub struct Cpu {
     pub interrupt_action: InterruptAction,
     pub interrupt_master_enabled: bool,
     pub is_halted: bool,
+    pub is_stopped: bool,
+    pub halt_bug: bool,
 }
 
 impl Cpu {
-    pub fn new() -> Cpu {
-        let registers = Registers::new();
+    pub fn new(hardware_mode: HardwareMode) -> Cpu {
+        let registers = Registers::new(hardware_mode);
 
         Cpu {
             registers,
             interrupt_action: InterruptAction::None,
             interrupt_master_enabled: false,
             is_halted: false,
+            is_stopped: false,
+            halt_bug: false,
         }
     }
 
-    pub fn step(&mut self, mmu: &mut Mmu) -> u8 {
+    /// Executes a single instruction and returns the clock cycles it took.
+    /// The rest of the system is ticked by the mmu on every memory access
+    pub fn step(&mut self, mmu: &mut Mmu) -> u32 {
+        if self.is_stopped {
+            if !mmu.joypad_line_low() {
+                mmu.tick_stopped();
+                return mmu.take_cycles_ticked();
+            }
+
+            self.is_stopped = false;
+        }
+
+        if self.is_halted && any_interrupt_fired(mmu) {
+            self.is_halted = false;
+
+            //Leaving HALT to service an interrupt takes an extra M-cycle
+            if self.interrupt_master_enabled {
+                mmu.tick(4);
+            }
+        }
+
+        if self.is_halted {
+            //Nothing can happen until the next event so skip ahead instead of ticking every M-cycle
+            let cycles = mmu.cycles_until_next_event();
+            mmu.tick(cycles);
+            return mmu.take_cycles_ticked();
+        }
+
+        if self.interrupt_master_enabled && handle_interrupts(self, mmu) {
+            return mmu.take_cycles_ticked();
+        }
+
+        //EI takes effect after the instruction following it
+        if let InterruptAction::Enable = self.interrupt_action {
+            self.interrupt_master_enabled = true;
+            self.interrupt_action = InterruptAction::None;
+        }
+
         let op_code = mmu.read_opcode(self.registers.pc);
 
+        if self.halt_bug {
+            //HALT Bug: PC is not incremented after the fetch so the next byte is read twice
+            self.halt_bug = false;
+            self.registers.pc = self.registers.pc.wrapping_sub(1);
+        }
+
         let instruction = match instructions::get_instruction_by_op_code(&op_code) {
             Some(instruction) => instruction,
             None => {
@@ -51,38 +98,11 @@ impl Cpu {
             }
         };
 
-        if self.is_halted && any_interrupt_fired(mmu) {
-            self.is_halted = false;
-            if !self.interrupt_master_enabled {
-                //HALT Bug
-                self.registers.pc -= 1;
-            }
-        }
-
-        if self.is_halted {
-            return 4;
-        }
-
-        if self.interrupt_master_enabled {
-            match handle_interrupts(self, mmu) {
-                Some(cycles) => return cycles,
-                None => {}
-            }
-        }
-
-        match self.interrupt_action {
-            InterruptAction::Enable => {
-                self.interrupt_master_enabled = true;
-                self.interrupt_action = InterruptAction::None;
-            }
-            InterruptAction::Disable => {
-                self.interrupt_master_enabled = false;
-                self.interrupt_action = InterruptAction::None;
-            }
-            _ => {}
-        }
+        let clock_cycles = self.execute_instruction(instruction, mmu, &op_code);
 
-        self.execute_instruction(instruction, mmu, &op_code)
+        //Internal cycles without a memory access are ticked after the instruction
+        mmu.tick((clock_cycles as u32).saturating_sub(mmu.cycles_ticked()) as u8);
+        mmu.take_cycles_ticked()
     }
 
     fn execute_instruction(
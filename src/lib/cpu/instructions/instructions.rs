@@ -1,4 +1,4 @@
-use crate::lib::cpu::cpu::{Cpu, InterruptAction};
+use crate::lib::cpu::cpu::{any_interrupt_fired, Cpu, InterruptAction};
 use crate::lib::cpu::instructions::functions::rotate_left;
 use crate::lib::cpu::instructions::{functions, read_hl_addr, ExecutionType, Instruction};
 use crate::lib::cpu::registers::Flag;
@@ -194,8 +194,17 @@ pub fn get_instruction(op_code: &u8) -> Option<&Instruction> {
             clock_cycles: 4,
             clock_cycles_condition: None,
             description: "STOP 0",
-            handler: |_cpu: &mut Cpu, _: &mut Mmu, _: &Opcode| {
-                //TODO: Implement stop
+            handler: |cpu: &mut Cpu, mmu: &mut Mmu, _: &Opcode| {
+                if mmu.speed_switch_prepared() {
+                    mmu.switch_speed();
+                    return ExecutionType::None;
+                }
+
+                //With a key already held down the cpu does not enter STOP
+                if !mmu.joypad_line_low() {
+                    cpu.is_stopped = true;
+                    mmu.enter_stop_mode();
+                }
                 ExecutionType::None
             },
         }),
@@ -672,7 +681,8 @@ pub fn get_instruction(op_code: &u8) -> Option<&Instruction> {
             description: "LD (HL),n",
             handler: |cpu: &mut Cpu, mmu: &mut Mmu, _: &Opcode| {
                 let addr = bytes_to_word(cpu.registers.h, cpu.registers.l);
-                mmu.write(addr, functions::get_argument(cpu, mmu, 0));
+                let value = functions::get_argument(cpu, mmu, 0);
+                mmu.write(addr, value);
                 ExecutionType::None
             },
         }),
@@ -1353,9 +1363,14 @@ pub fn get_instruction(op_code: &u8) -> Option<&Instruction> {
             clock_cycles: 4,
             clock_cycles_condition: None,
             description: "HALT",
-            handler: |cpu: &mut Cpu, _: &mut Mmu, _: &Opcode| {
-                //TODO: Halt instructions slows emulation down. Check why. Also HALT Bug seems to be implemented wrong
-                cpu.is_halted = true;
+            handler: |cpu: &mut Cpu, mmu: &mut Mmu, _: &Opcode| {
+                //With IME disabled and an interrupt already pending the cpu does not halt
+                //but fails to increment PC after reading the next opcode (HALT Bug)
+                if !cpu.interrupt_master_enabled && any_interrupt_fired(mmu) {
+                    cpu.halt_bug = true;
+                } else {
+                    cpu.is_halted = true;
+                }
                 ExecutionType::None
             },
         }),
@@ -2112,8 +2127,9 @@ pub fn get_instruction(op_code: &u8) -> Option<&Instruction> {
             description: "RET NZ",
             handler: |cpu: &mut Cpu, mmu: &mut Mmu, _: &Opcode| {
                 if !cpu.registers.check_flag(Flag::Z) {
-                    cpu.registers.pc = mmu.read_word(cpu.registers.sp);
-                    cpu.registers.sp += 2;
+                    //Checking the condition takes an internal M-cycle
+                    mmu.tick(4);
+                    cpu.registers.pc = functions::pop_word(cpu, mmu);
                     return ExecutionType::JumpedActionTaken;
                 }
 
@@ -2126,9 +2142,10 @@ pub fn get_instruction(op_code: &u8) -> Option<&Instruction> {
             clock_cycles_condition: None,
             description: "POP BC",
             handler: |cpu: &mut Cpu, mmu: &mut Mmu, _: &Opcode| {
-                cpu.registers.b = mmu.read(cpu.registers.sp + 0x01);
-                cpu.registers.c = mmu.read(cpu.registers.sp);
-                cpu.registers.sp += 2;
+                let value = functions::pop_word(cpu, mmu);
+                let (byte1, byte2) = word_to_bytes(value);
+                cpu.registers.b = byte1;
+                cpu.registers.c = byte2;
                 ExecutionType::None
             },
         }),
@@ -2182,11 +2199,8 @@ pub fn get_instruction(op_code: &u8) -> Option<&Instruction> {
             clock_cycles_condition: None,
             description: "PUSH BC",
             handler: |cpu: &mut Cpu, mmu: &mut Mmu, _: &Opcode| {
-                cpu.registers.sp -= 2;
-                mmu.write_word(
-                    cpu.registers.sp,
-                    binary::bytes_to_word(cpu.registers.b, cpu.registers.c),
-                );
+                let value = binary::bytes_to_word(cpu.registers.b, cpu.registers.c);
+                functions::push_word(cpu, mmu, value);
                 ExecutionType::None
             },
         }),
@@ -2221,8 +2235,9 @@ pub fn get_instruction(op_code: &u8) -> Option<&Instruction> {
             description: "RET Z",
             handler: |cpu: &mut Cpu, mmu: &mut Mmu, _: &Opcode| {
                 if cpu.registers.check_flag(Flag::Z) {
-                    cpu.registers.pc = mmu.read_word(cpu.registers.sp);
-                    cpu.registers.sp += 2;
+                    //Checking the condition takes an internal M-cycle
+                    mmu.tick(4);
+                    cpu.registers.pc = functions::pop_word(cpu, mmu);
                     return ExecutionType::JumpedActionTaken;
                 }
 
@@ -2235,8 +2250,7 @@ pub fn get_instruction(op_code: &u8) -> Option<&Instruction> {
             clock_cycles_condition: None,
             description: "RET",
             handler: |cpu: &mut Cpu, mmu: &mut Mmu, _: &Opcode| {
-                cpu.registers.pc = mmu.read_word(cpu.registers.sp);
-                cpu.registers.sp = cpu.registers.sp.wrapping_add(2);
+                cpu.registers.pc = functions::pop_word(cpu, mmu);
                 ExecutionType::Jumped
             },
         }),
@@ -2311,8 +2325,9 @@ pub fn get_instruction(op_code: &u8) -> Option<&Instruction> {
             description: "RET NC",
             handler: |cpu: &mut Cpu, mmu: &mut Mmu, _: &Opcode| {
                 if !cpu.registers.check_flag(Flag::C) {
-                    cpu.registers.pc = mmu.read_word(cpu.registers.sp);
-                    cpu.registers.sp += 2;
+                    //Checking the condition takes an internal M-cycle
+                    mmu.tick(4);
+                    cpu.registers.pc = functions::pop_word(cpu, mmu);
                     return ExecutionType::JumpedActionTaken;
                 }
 
@@ -2325,9 +2340,10 @@ pub fn get_instruction(op_code: &u8) -> Option<&Instruction> {
             clock_cycles_condition: None,
             description: "POP DE",
             handler: |cpu: &mut Cpu, mmu: &mut Mmu, _: &Opcode| {
-                cpu.registers.d = mmu.read(cpu.registers.sp + 1);
-                cpu.registers.e = mmu.read(cpu.registers.sp);
-                cpu.registers.sp += 2;
+                let value = functions::pop_word(cpu, mmu);
+                let (byte1, byte2) = word_to_bytes(value);
+                cpu.registers.d = byte1;
+                cpu.registers.e = byte2;
                 ExecutionType::None
             },
         }),
@@ -2367,11 +2383,8 @@ pub fn get_instruction(op_code: &u8) -> Option<&Instruction> {
             clock_cycles_condition: None,
             description: "PUSH DE",
             handler: |cpu: &mut Cpu, mmu: &mut Mmu, _: &Opcode| {
-                cpu.registers.sp -= 2;
-                mmu.write_word(
-                    cpu.registers.sp,
-                    binary::bytes_to_word(cpu.registers.d, cpu.registers.e),
-                );
+                let value = binary::bytes_to_word(cpu.registers.d, cpu.registers.e);
+                functions::push_word(cpu, mmu, value);
                 ExecutionType::None
             },
         }),
@@ -2406,8 +2419,9 @@ pub fn get_instruction(op_code: &u8) -> Option<&Instruction> {
             description: "RET C",
             handler: |cpu: &mut Cpu, mmu: &mut Mmu, _: &Opcode| {
                 if cpu.registers.check_flag(Flag::C) {
-                    cpu.registers.pc = mmu.read_word(cpu.registers.sp);
-                    cpu.registers.sp += 2;
+                    //Checking the condition takes an internal M-cycle
+                    mmu.tick(4);
+                    cpu.registers.pc = functions::pop_word(cpu, mmu);
                     return ExecutionType::JumpedActionTaken;
                 }
 
@@ -2420,9 +2434,9 @@ pub fn get_instruction(op_code: &u8) -> Option<&Instruction> {
             clock_cycles_condition: None,
             description: "RETI",
             handler: |cpu: &mut Cpu, mmu: &mut Mmu, _: &Opcode| {
-                cpu.interrupt_action = InterruptAction::Enable;
-                cpu.registers.pc = mmu.read_word(cpu.registers.sp);
-                cpu.registers.sp += 2;
+                //Unlike EI, RETI enables interrupts immediately
+                cpu.interrupt_master_enabled = true;
+                cpu.registers.pc = functions::pop_word(cpu, mmu);
                 ExecutionType::Jumped
             },
         }),
@@ -2486,10 +2500,8 @@ pub fn get_instruction(op_code: &u8) -> Option<&Instruction> {
             clock_cycles_condition: None,
             description: "LDH (a8),A",
             handler: |cpu: &mut Cpu, mmu: &mut Mmu, _: &Opcode| {
-                mmu.write(
-                    0xFF00 + functions::get_argument(cpu, mmu, 0) as u16,
-                    cpu.registers.a,
-                );
+                let addr = 0xFF00 + functions::get_argument(cpu, mmu, 0) as u16;
+                mmu.write(addr, cpu.registers.a);
                 ExecutionType::None
             },
         }),
@@ -2499,9 +2511,10 @@ pub fn get_instruction(op_code: &u8) -> Option<&Instruction> {
             clock_cycles_condition: None,
             description: "POP HL",
             handler: |cpu: &mut Cpu, mmu: &mut Mmu, _: &Opcode| {
-                cpu.registers.h = mmu.read(cpu.registers.sp + 1);
-                cpu.registers.l = mmu.read(cpu.registers.sp);
-                cpu.registers.sp += 2;
+                let value = functions::pop_word(cpu, mmu);
+                let (byte1, byte2) = word_to_bytes(value);
+                cpu.registers.h = byte1;
+                cpu.registers.l = byte2;
                 ExecutionType::None
             },
         }),
@@ -2521,11 +2534,8 @@ pub fn get_instruction(op_code: &u8) -> Option<&Instruction> {
             clock_cycles_condition: None,
             description: "PUSH HL",
             handler: |cpu: &mut Cpu, mmu: &mut Mmu, _: &Opcode| {
-                cpu.registers.sp -= 2;
-                mmu.write_word(
-                    cpu.registers.sp,
-                    binary::bytes_to_word(cpu.registers.h, cpu.registers.l),
-                );
+                let value = binary::bytes_to_word(cpu.registers.h, cpu.registers.l);
+                functions::push_word(cpu, mmu, value);
                 ExecutionType::None
             },
         }),
@@ -2617,7 +2627,8 @@ pub fn get_instruction(op_code: &u8) -> Option<&Instruction> {
             clock_cycles_condition: None,
             description: "LDH A,(a8)",
             handler: |cpu: &mut Cpu, mmu: &mut Mmu, _: &Opcode| {
-                cpu.registers.a = mmu.read(0xFF00 + (functions::get_argument(cpu, mmu, 0) as u16));
+                let addr = 0xFF00 + functions::get_argument(cpu, mmu, 0) as u16;
+                cpu.registers.a = mmu.read(addr);
                 ExecutionType::None
             },
         }),
@@ -2627,10 +2638,10 @@ pub fn get_instruction(op_code: &u8) -> Option<&Instruction> {
             clock_cycles_condition: None,
             description: "POP AF",
             handler: |cpu: &mut Cpu, mmu: &mut Mmu, _: &Opcode| {
-                cpu.registers.a = mmu.read(cpu.registers.sp + 1);
+                let (byte1, byte2) = word_to_bytes(functions::pop_word(cpu, mmu));
+                cpu.registers.a = byte1;
                 //Only the upper 4 bits are writable
-                cpu.registers.f = 0xF0 & mmu.read(cpu.registers.sp);
-                cpu.registers.sp += 2;
+                cpu.registers.f = 0xF0 & byte2;
                 ExecutionType::None
             },
         }),
@@ -2650,7 +2661,9 @@ pub fn get_instruction(op_code: &u8) -> Option<&Instruction> {
             clock_cycles_condition: None,
             description: "DI",
             handler: |cpu: &mut Cpu, _: &mut Mmu, _: &Opcode| {
-                cpu.interrupt_action = InterruptAction::Disable;
+                //DI takes effect immediately and cancels a pending EI
+                cpu.interrupt_master_enabled = false;
+                cpu.interrupt_action = InterruptAction::None;
                 ExecutionType::None
             },
         }),
@@ -2660,11 +2673,8 @@ pub fn get_instruction(op_code: &u8) -> Option<&Instruction> {
             clock_cycles_condition: None,
             description: "PUSH AF",
             handler: |cpu: &mut Cpu, mmu: &mut Mmu, _: &Opcode| {
-                cpu.registers.sp -= 2;
-                mmu.write_word(
-                    cpu.registers.sp,
-                    binary::bytes_to_word(cpu.registers.a, cpu.registers.f),
-                );
+                let value = binary::bytes_to_word(cpu.registers.a, cpu.registers.f);
+                functions::push_word(cpu, mmu, value);
                 ExecutionType::None
             },
         }),
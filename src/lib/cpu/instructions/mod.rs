@@ -3,7 +3,7 @@ use crate::lib::memory::mmu::{Mmu, Opcode};
 use crate::lib::util::binary::bytes_to_word;
 
 pub mod cb_instructions;
-mod functions;
+pub mod functions;
 pub mod instructions;
 
 pub enum ExecutionType {
@@ -28,7 +28,7 @@ pub fn get_instruction_by_op_code(op_code: &Opcode) -> Option<&Instruction> {
     }
 }
 
-fn read_hl_addr(cpu: &Cpu, mmu: &Mmu) -> u8 {
+fn read_hl_addr(cpu: &Cpu, mmu: &mut Mmu) -> u8 {
     mmu.read(bytes_to_word(cpu.registers.h, cpu.registers.l))
 }
 
@@ -1,39 +1,35 @@
 use crate::lib::cpu::cpu::Cpu;
-use crate::lib::memory::interrupts::Interrupt;
 use crate::lib::memory::mmu::Mmu;
 
-pub fn handle_interrupts(cpu: &mut Cpu, mmu: &mut Mmu) -> Option<u8> {
-    if handle_interrupt(cpu, mmu, &Interrupt::Vblank, 0x0040) {
-        return Some(12);
+/// Dispatches the pending interrupt with the highest priority. Takes 5 M-cycles:
+/// two wait states, pushing pc high and low byte and setting pc to the isr address
+pub fn handle_interrupts(cpu: &mut Cpu, mmu: &mut Mmu) -> bool {
+    if mmu.interrupts.pending_interrupt().is_none() {
+        return false;
     }
 
-    if handle_interrupt(cpu, mmu, &Interrupt::LcdStat, 0x0048) {
-        return Some(12);
-    }
+    cpu.interrupt_master_enabled = false;
+    mmu.tick(8);
 
-    if handle_interrupt(cpu, mmu, &Interrupt::Timer, 0x0050) {
-        return Some(12);
-    }
+    let pc = cpu.registers.pc;
+    cpu.registers.sp = cpu.registers.sp.wrapping_sub(1);
+    mmu.write(cpu.registers.sp, (pc >> 8) as u8);
 
-    if handle_interrupt(cpu, mmu, &Interrupt::Serial, 0x0058) {
-        return Some(12);
-    }
+    //The interrupt is chosen after the high byte has been pushed.
+    //If the push overwrote IE the interrupt can change or get cancelled and pc is set to 0x0000
+    let interrupt = mmu.interrupts.pending_interrupt();
 
-    if handle_interrupt(cpu, mmu, &Interrupt::Joypad, 0x0060) {
-        return Some(12);
-    }
+    cpu.registers.sp = cpu.registers.sp.wrapping_sub(1);
+    mmu.write(cpu.registers.sp, pc as u8);
 
-    return None;
-}
+    cpu.registers.pc = match interrupt {
+        Some(interrupt) => {
+            mmu.interrupts.reset_interrupt(&interrupt);
+            interrupt.isr_address()
+        }
+        None => 0x0000,
+    };
 
-fn handle_interrupt(cpu: &mut Cpu, mmu: &mut Mmu, interrupt: &Interrupt, isr_address: u16) -> bool {
-    if !mmu.interrupts.interrupt_fired(interrupt) {
-        return false;
-    }
-    cpu.interrupt_master_enabled = false;
-    cpu.registers.sp -= 2;
-    mmu.write_word(cpu.registers.sp, cpu.registers.pc);
-    cpu.registers.pc = isr_address;
-    mmu.interrupts.reset_interrupt(interrupt);
+    mmu.tick(4);
     true
 }
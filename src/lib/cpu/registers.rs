@@ -1,3 +1,5 @@
+use crate::lib::emulation::HardwareMode;
+
 pub enum Flag {
     Z = 0x80,
     N = 0x40,
@@ -19,19 +21,45 @@ pub struct Registers {
 }
 
 impl Registers {
-    pub fn new() -> Registers {
+    pub fn new(hardware_mode: HardwareMode) -> Registers {
         //Set initial values according to pandocs
-        Registers {
-            a: 0x01,
-            b: 0x00,
-            c: 0x13,
-            d: 0x00,
-            e: 0xD8,
-            h: 0x01,
-            l: 0x4D,
-            f: 0xB0,
-            pc: 0x100,
-            sp: 0xFFFE,
+        match hardware_mode {
+            HardwareMode::Dmg => Registers {
+                a: 0x01,
+                b: 0x00,
+                c: 0x13,
+                d: 0x00,
+                e: 0xD8,
+                h: 0x01,
+                l: 0x4D,
+                f: 0xB0,
+                pc: 0x100,
+                sp: 0xFFFE,
+            },
+            HardwareMode::Sgb => Registers {
+                a: 0x01,
+                b: 0x00,
+                c: 0x14,
+                d: 0x00,
+                e: 0x00,
+                h: 0xC0,
+                l: 0x60,
+                f: 0x00,
+                pc: 0x100,
+                sp: 0xFFFE,
+            },
+            HardwareMode::Cgb => Registers {
+                a: 0x11,
+                b: 0x00,
+                c: 0x00,
+                d: 0xFF,
+                e: 0x56,
+                h: 0x00,
+                l: 0x0D,
+                f: 0x80,
+                pc: 0x100,
+                sp: 0xFFFE,
+            },
         }
     }
 
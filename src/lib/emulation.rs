@@ -1,9 +1,31 @@
 use super::{clock::Clock, io::joypad::Joypad, memory::mmu::Mmu};
 use crate::lib::cpu::cpu::Cpu;
 
+//Clock of the cpu in normal speed mode. The CGB double speed mode runs the cpu at twice this rate
 pub const CPU_CLOCK_HZ: usize = 4194304;
 pub const FPS: f32 = 60.0;
 
+#[derive(Copy, Clone, PartialEq)]
+pub enum HardwareMode {
+    Dmg,
+    Cgb,
+    Sgb,
+}
+
+impl HardwareMode {
+    /// Bit 7 of the CGB flag in the cartridge header marks a game with CGB functions.
+    /// SGB functions need the SGB flag 0x03 and the old licensee code 0x33
+    pub fn from_header(cgb_flag: u8, sgb_flag: u8, old_licensee_code: u8) -> HardwareMode {
+        if cgb_flag & 0x80 == 0x80 {
+            HardwareMode::Cgb
+        } else if sgb_flag == 0x03 && old_licensee_code == 0x33 {
+            HardwareMode::Sgb
+        } else {
+            HardwareMode::Dmg
+        }
+    }
+}
+
 pub struct Emulation {
     clock: Clock,
 }
@@ -19,9 +41,9 @@ impl Emulation {
     /// Execute in a loop
     pub fn cycle(&mut self, cpu: &mut Cpu, mmu: &mut Mmu, joypad: &Joypad) {
         while self.clock.clock_cycles_passed_frame <= self.clock.clock_cycles_per_frame {
+            mmu.read_joypad(joypad);
             let last_cycle = cpu.step(mmu);
-            mmu.step(joypad, last_cycle);
-            self.clock.cycle(last_cycle);
+            self.clock.cycle(last_cycle, mmu.is_double_speed());
         }
 
         self.clock.reset();
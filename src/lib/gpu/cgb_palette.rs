@@ -0,0 +1,71 @@
+const PALETTE_RAM_SIZE: usize = 64;
+
+/// Palette RAM of the CGB. 8 palettes with 4 colors each, stored as 15 bit RGB little endian.
+/// Accessed through a specification register (BCPS/OCPS) and a data register (BCPD/OCPD)
+pub struct CgbPalette {
+    data: [u8; PALETTE_RAM_SIZE],
+    index: u8,
+    auto_increment: bool,
+}
+
+impl Default for CgbPalette {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl CgbPalette {
+    pub fn new() -> CgbPalette {
+        CgbPalette {
+            data: [0xFF; PALETTE_RAM_SIZE],
+            index: 0,
+            auto_increment: false,
+        }
+    }
+
+    pub fn set_specification(&mut self, value: u8) {
+        self.index = value & 0x3F;
+        self.auto_increment = value & 0x80 == 0x80;
+    }
+
+    pub fn get_specification(&self) -> u8 {
+        let auto_increment = if self.auto_increment { 0x80 } else { 0 };
+        auto_increment | 0x40 | self.index
+    }
+
+    pub fn write_data(&mut self, value: u8) {
+        self.data[self.index as usize] = value;
+
+        //Only writes increment the index
+        if self.auto_increment {
+            self.index = (self.index + 1) & 0x3F;
+        }
+    }
+
+    pub fn read_data(&self) -> u8 {
+        self.data[self.index as usize]
+    }
+
+    /// Returns the color as 8 bit RGB
+    pub fn get_color(&self, palette: u8, color_index: u8) -> [u8; 3] {
+        let offset = (palette as usize & 0x07) * 8 + color_index as usize * 2;
+        let color = self.data[offset] as u16 | (self.data[offset + 1] as u16) << 8;
+
+        convert_color(color)
+    }
+}
+
+/// Converts a 15 bit color to 8 bit RGB. Used by the SGB as well
+pub fn convert_color(color: u16) -> [u8; 3] {
+    [
+        convert_color_channel(color),
+        convert_color_channel(color >> 5),
+        convert_color_channel(color >> 10),
+    ]
+}
+
+/// Scales a 5 bit color channel to 8 bit
+fn convert_color_channel(value: u16) -> u8 {
+    let channel = (value & 0x1F) as u8;
+    channel << 3 | channel >> 2
+}
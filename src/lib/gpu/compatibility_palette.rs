@@ -0,0 +1,303 @@
+use crate::lib::cartridge::{NEW_LICENSEE_CODE_ADDRESS, OLD_LICENSEE_CODE_ADDRESS, TITLE_ADDRESS};
+use crate::lib::gpu::cgb_palette::convert_color;
+
+//The title checksum is calculated over all 16 title bytes including the CGB flag
+const TITLE_CHECKSUM_LENGTH: usize = 16;
+const NINTENDO_LICENSEE_CODE: u8 = 0x01;
+//Old licensee code telling that the new licensee code is used instead
+const USE_NEW_LICENSEE_CODE: u8 = 0x33;
+
+//Checksums from index 65 on are used by several titles and need the 4th letter of the title
+const FIRST_DUPLICATE_CHECKSUM: usize = 65;
+//The 4th letters of the duplicate checksums are stored in rows of this length
+const FOURTH_LETTER_ROW_LENGTH: usize = 14;
+
+//RGB555 colors of the CGB boot rom, 4 per palette
+const BOOT_ROM_COLORS: [u16; 120] = [
+    0x7FFF, 0x32BF, 0x00D0, 0x0000, //0
+    0x639F, 0x4279, 0x15B0, 0x04CB, //1
+    0x7FFF, 0x6E31, 0x454A, 0x0000, //2
+    0x7FFF, 0x1BEF, 0x0200, 0x0000, //3
+    0x7FFF, 0x421F, 0x1CF2, 0x0000, //4
+    0x7FFF, 0x5294, 0x294A, 0x0000, //5
+    0x7FFF, 0x03FF, 0x012F, 0x0000, //6
+    0x7FFF, 0x03EF, 0x01D6, 0x0000, //7
+    0x7FFF, 0x42B5, 0x3DC8, 0x0000, //8
+    0x7E74, 0x03FF, 0x0180, 0x0000, //9
+    0x67FF, 0x77AC, 0x1A13, 0x2D6B, //10
+    0x7ED6, 0x4BFF, 0x2175, 0x0000, //11
+    0x53FF, 0x4A5F, 0x7E52, 0x0000, //12
+    0x4FFF, 0x7ED2, 0x3A4C, 0x1CE0, //13
+    0x03ED, 0x7FFF, 0x255F, 0x0000, //14
+    0x036A, 0x021F, 0x03FF, 0x7FFF, //15
+    0x7FFF, 0x01DF, 0x0112, 0x0000, //16
+    0x231F, 0x035F, 0x00F2, 0x0009, //17
+    0x7FFF, 0x03EA, 0x011F, 0x0000, //18
+    0x299F, 0x001A, 0x000C, 0x0000, //19
+    0x7FFF, 0x027F, 0x001F, 0x0000, //20
+    0x7FFF, 0x03E0, 0x0206, 0x0120, //21
+    0x7FFF, 0x7EEB, 0x001F, 0x7C00, //22
+    0x7FFF, 0x3FFF, 0x7E00, 0x001F, //23
+    0x7FFF, 0x03FF, 0x001F, 0x0000, //24
+    0x03FF, 0x001F, 0x000C, 0x0000, //25
+    0x7FFF, 0x033F, 0x0193, 0x0000, //26
+    0x0000, 0x4200, 0x037F, 0x7FFF, //27
+    0x7FFF, 0x7E8C, 0x7C00, 0x0000, //28
+    0x7FFF, 0x1BEF, 0x6180, 0x0000, //29
+];
+
+/// Background, OBJ0 and OBJ1 colors as index of their first color in BOOT_ROM_COLORS
+struct PaletteCombination {
+    background: usize,
+    object0: usize,
+    object1: usize,
+}
+
+/// Combination of 3 whole boot rom palettes
+const fn combination(background: usize, object0: usize, object1: usize) -> PaletteCombination {
+    PaletteCombination {
+        background: background * 4,
+        object0: object0 * 4,
+        object1: object1 * 4,
+    }
+}
+
+//A few combinations of the boot rom start in the middle of a palette
+const PALETTE_COMBINATIONS: [PaletteCombination; 51] = [
+    combination(29, 4, 4),
+    combination(18, 18, 18),
+    combination(20, 20, 20),
+    combination(24, 24, 24),
+    combination(9, 9, 9),
+    combination(0, 0, 0),
+    combination(27, 27, 27),
+    combination(5, 5, 5),
+    combination(12, 12, 12),
+    combination(26, 26, 26),
+    combination(8, 16, 8),
+    combination(28, 4, 28),
+    combination(2, 4, 2),
+    combination(4, 3, 4),
+    combination(29, 4, 29),
+    combination(28, 28, 4),
+    combination(2, 2, 17),
+    combination(8, 16, 16),
+    combination(7, 4, 4),
+    combination(18, 4, 4),
+    combination(20, 4, 4),
+    combination(9, 19, 19),
+    PaletteCombination {
+        background: 44,
+        object0: 15,
+        object1: 15,
+    },
+    combination(2, 17, 17),
+    combination(2, 4, 4),
+    combination(3, 4, 4),
+    combination(0, 28, 28),
+    combination(0, 3, 3),
+    combination(1, 0, 0),
+    combination(18, 18, 22),
+    combination(20, 20, 22),
+    combination(24, 24, 22),
+    combination(8, 16, 22),
+    combination(13, 17, 4),
+    PaletteCombination {
+        background: 56,
+        object0: 111,
+        object1: 0,
+    },
+    PaletteCombination {
+        background: 60,
+        object0: 111,
+        object1: 16,
+    },
+    combination(9, 19, 22),
+    combination(10, 16, 28),
+    combination(28, 4, 23),
+    combination(2, 17, 22),
+    combination(2, 4, 0),
+    combination(3, 4, 28),
+    combination(0, 28, 3),
+    combination(4, 3, 28),
+    combination(4, 21, 28),
+    combination(0, 3, 28),
+    combination(28, 25, 3),
+    combination(8, 0, 28),
+    combination(28, 4, 3),
+    combination(6, 28, 3),
+    combination(29, 4, 28),
+];
+
+//Title checksums of games by Nintendo
+const TITLE_CHECKSUMS: [u8; 79] = [
+    0x00, 0x88, 0x16, 0x36, 0xD1, 0xDB, 0xF2, 0x3C, 0x8C, 0x92, 0x3D, 0x5C, 0x58, 0xC9, 0x3E, 0x70,
+    0x1D, 0x59, 0x69, 0x19, 0x35, 0xA8, 0x14, 0xAA, 0x75, 0x95, 0x99, 0x34, 0x6F, 0x15, 0xFF, 0x97,
+    0x4B, 0x90, 0x17, 0x10, 0x39, 0xF7, 0xF6, 0xA2, 0x49, 0x4E, 0x43, 0x68, 0xE0, 0x8B, 0xF0, 0xCE,
+    0x0C, 0x29, 0xE8, 0xB7, 0x86, 0x9A, 0x52, 0x01, 0x9D, 0x71, 0x9C, 0xBD, 0x5D, 0x6D, 0x67, 0x3F,
+    0x6B, 0xB3, 0x46, 0x28, 0xA5, 0xC6, 0xD3, 0x27, 0x61, 0x18, 0x66, 0x6A, 0xBF, 0x0D, 0xF4,
+];
+
+//4th letters of the titles with a duplicate checksum. The n-th letter of every row belongs to
+//the n-th duplicate checksum
+const FOURTH_LETTERS: &[u8; 29] = b"BEFAARBEKEK R-URAR INAILICE R";
+
+//Palette combination of every unique checksum followed by every 4th letter
+const PALETTE_INDICES: [u8; 94] = [
+    0, 4, 5, 35, 34, 3, 31, 15, 10, 5, 19, 36, 7, 37, 30, 44, 21, 32, 31, 20, 5, 33, 13, 14, 5, 29,
+    5, 18, 9, 3, 2, 26, 25, 25, 41, 42, 26, 45, 42, 45, 36, 38, 26, 42, 30, 41, 34, 34, 5, 42, 6,
+    5, 33, 25, 42, 42, 40, 2, 16, 25, 42, 42, 5, 0, 39, 36, 22, 25, 6, 32, 12, 36, 11, 39, 18, 39,
+    24, 31, 50, 17, 46, 6, 27, 0, 47, 41, 41, 0, 0, 19, 34, 23, 18, 29,
+];
+
+/// Colors used for a DMG game on the CGB. Background, OBP0 and OBP1 each get their own 4 colors.
+/// Color 0 is the lightest shade
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CompatibilityPalette {
+    pub background: [[u8; 3]; 4],
+    pub object0: [[u8; 3]; 4],
+    pub object1: [[u8; 3]; 4],
+}
+
+impl CompatibilityPalette {
+    /// Uses the same colors for background and sprites like the DMG
+    pub fn monochrome(colors: [[u8; 3]; 4]) -> CompatibilityPalette {
+        CompatibilityPalette {
+            background: colors,
+            object0: colors,
+            object1: colors,
+        }
+    }
+
+    /// Picks the palette like the CGB boot rom. Games by Nintendo are identified by the checksum
+    /// of their title. Duplicate checksums are told apart by the 4th letter of the title.
+    /// Every other game gets the default palette
+    pub fn from_header(rom: &[u8]) -> CompatibilityPalette {
+        let default_palette = ManualPalette::RightA.palette();
+
+        if !is_licensed_by_nintendo(rom) {
+            return default_palette;
+        }
+
+        let title = &rom[TITLE_ADDRESS..TITLE_ADDRESS + TITLE_CHECKSUM_LENGTH];
+        let checksum = title
+            .iter()
+            .fold(0u8, |checksum, &value| checksum.wrapping_add(value));
+
+        //The first entry only holds the default palette
+        let checksum_index = TITLE_CHECKSUMS
+            .iter()
+            .skip(1)
+            .position(|&entry| entry == checksum)
+            .map(|index| index + 1);
+        let palette_index = match checksum_index {
+            Some(index) if index < FIRST_DUPLICATE_CHECKSUM => Some(index),
+            Some(index) => (index - FIRST_DUPLICATE_CHECKSUM..FOURTH_LETTERS.len())
+                .step_by(FOURTH_LETTER_ROW_LENGTH)
+                .find(|&letter| FOURTH_LETTERS[letter] == title[3])
+                .map(|letter| FIRST_DUPLICATE_CHECKSUM + letter),
+            None => None,
+        };
+
+        palette_index.map_or(default_palette, |index| {
+            Self::from_combination(PALETTE_INDICES[index] as usize)
+        })
+    }
+
+    fn from_combination(index: usize) -> CompatibilityPalette {
+        let combination = &PALETTE_COMBINATIONS[index];
+        CompatibilityPalette {
+            background: boot_rom_colors(combination.background),
+            object0: boot_rom_colors(combination.object0),
+            object1: boot_rom_colors(combination.object1),
+        }
+    }
+}
+
+fn boot_rom_colors(start: usize) -> [[u8; 3]; 4] {
+    let mut colors = [[0; 3]; 4];
+    for (index, color) in colors.iter_mut().enumerate() {
+        *color = convert_color(BOOT_ROM_COLORS[start + index]);
+    }
+    colors
+}
+
+fn is_licensed_by_nintendo(rom: &[u8]) -> bool {
+    match rom[OLD_LICENSEE_CODE_ADDRESS] {
+        NINTENDO_LICENSEE_CODE => true,
+        USE_NEW_LICENSEE_CODE => {
+            &rom[NEW_LICENSEE_CODE_ADDRESS..NEW_LICENSEE_CODE_ADDRESS + 2] == b"01"
+        }
+        _ => false,
+    }
+}
+
+/// Palettes selectable on the CGB boot screen by holding a direction and optionally A or B
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ManualPalette {
+    Up,
+    UpA,
+    UpB,
+    Left,
+    LeftA,
+    LeftB,
+    Down,
+    DownA,
+    DownB,
+    Right,
+    RightA,
+    RightB,
+}
+
+pub const MANUAL_PALETTES: [ManualPalette; 12] = [
+    ManualPalette::Up,
+    ManualPalette::UpA,
+    ManualPalette::UpB,
+    ManualPalette::Left,
+    ManualPalette::LeftA,
+    ManualPalette::LeftB,
+    ManualPalette::Down,
+    ManualPalette::DownA,
+    ManualPalette::DownB,
+    ManualPalette::Right,
+    ManualPalette::RightA,
+    ManualPalette::RightB,
+];
+
+impl ManualPalette {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ManualPalette::Up => "Brown",
+            ManualPalette::UpA => "Red",
+            ManualPalette::UpB => "Dark Brown",
+            ManualPalette::Left => "Blue",
+            ManualPalette::LeftA => "Dark Blue",
+            ManualPalette::LeftB => "Grayscale",
+            ManualPalette::Down => "Pastel Mix",
+            ManualPalette::DownA => "Orange",
+            ManualPalette::DownB => "Yellow",
+            ManualPalette::Right => "Green",
+            ManualPalette::RightA => "Dark Green",
+            ManualPalette::RightB => "Inverted",
+        }
+    }
+
+    pub fn palette(&self) -> CompatibilityPalette {
+        //Palette combinations the boot rom uses for the button combinations
+        let index = match self {
+            ManualPalette::Up => 5,
+            ManualPalette::UpA => 43,
+            ManualPalette::UpB => 28,
+            ManualPalette::Left => 48,
+            ManualPalette::LeftA => 40,
+            ManualPalette::LeftB => 7,
+            ManualPalette::Down => 8,
+            ManualPalette::DownA => 3,
+            ManualPalette::DownB => 49,
+            ManualPalette::Right => 1,
+            ManualPalette::RightA => 0,
+            ManualPalette::RightB => 6,
+        };
+        CompatibilityPalette::from_combination(index)
+    }
+}
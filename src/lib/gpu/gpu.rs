@@ -1,20 +1,25 @@
 use std::sync::Arc;
 
 use crate::lib::{
+    emulation::HardwareMode,
     memory::{
         interrupts::Interrupt,
         mmu::{OAM_ADDRESS, VRAM_ADDRESS},
     },
+    sgb::Sgb,
     util::binary::is_bit_set,
 };
 
 use super::{
+    cgb_palette::CgbPalette,
+    compatibility_palette::CompatibilityPalette,
     lcdc::Lcdc,
     stat::{Mode, Stat},
-    Screen, BUFFER_SIZE, SCREEN_WIDTH,
+    Screen, BUFFER_SIZE, SCREEN_HEIGHT, SCREEN_WIDTH,
 };
 
 const V_RAM_SIZE: usize = 8192;
+const V_RAM_BANKS: usize = 2;
 const OAM_SIZE: usize = 160;
 
 const TILESET_FIRST_BEGIN_ADDRESS: u16 = 0x8000;
@@ -34,6 +39,8 @@ const MAX_SCANLINES: u8 = 153;
 enum PriorityFlag {
     None,
     Color0,
+    //CGB BG attribute bit 7 puts colors 1-3 above all sprites
+    BackgroundPriority,
 }
 
 pub struct Gpu {
@@ -46,17 +53,26 @@ pub struct Gpu {
     pub window_x: u8,
     pub window_y: u8,
     pub interrupts_fired: u8,
+    pub hblank_started: bool,
+    pub background_palette: CgbPalette,
+    pub object_palette: CgbPalette,
+    pub sgb: Sgb,
+    hardware_mode: HardwareMode,
     clock: u16,
     screen_buffer: [u8; BUFFER_SIZE],
+    //Shades after applying BGP/OBP0/OBP1. The SGB colors these itself
+    shade_buffer: [u8; SCREEN_WIDTH * SCREEN_HEIGHT],
     bg_priority_map: [PriorityFlag; 65792],
-    v_ram: [u8; V_RAM_SIZE],
+    v_ram: [u8; V_RAM_SIZE * V_RAM_BANKS],
+    vram_bank: u8,
+    object_priority_mode: u8,
     oam: [u8; OAM_SIZE],
     lyc: u8,
     bg_pal: [u8; 4],
     sprite_palette0: [u8; 4],
     sprite_palette1: [u8; 4],
     raw_palette_data: [u8; 3],
-    color_map: [[u8; 3]; 4],
+    dmg_palette: CompatibilityPalette,
     lcd_enabled: bool,
     first_frame_after_activation: bool,
 }
@@ -74,27 +90,63 @@ impl Gpu {
             window_x: 7,
             lyc: 0,
             interrupts_fired: 0,
+            hblank_started: false,
+            background_palette: CgbPalette::new(),
+            object_palette: CgbPalette::new(),
+            sgb: Sgb::new(),
+            hardware_mode: HardwareMode::Dmg,
             clock: 0,
             screen_buffer: [0; BUFFER_SIZE],
+            shade_buffer: [0; SCREEN_WIDTH * SCREEN_HEIGHT],
             bg_priority_map: [PriorityFlag::None; 65792],
-            v_ram: [0; V_RAM_SIZE],
+            v_ram: [0; V_RAM_SIZE * V_RAM_BANKS],
+            vram_bank: 0,
+            object_priority_mode: 0,
             oam: [0; OAM_SIZE],
             bg_pal: [0, 1, 2, 3],
             sprite_palette0: [0, 1, 2, 3],
             sprite_palette1: [0, 1, 2, 3],
             raw_palette_data: [0xFC, 0xFF, 0xFF],
-            color_map: [[0; 3], [0; 3], [0; 3], [0; 3]],
+            dmg_palette: CompatibilityPalette::monochrome([[0; 3]; 4]),
             lcd_enabled: true,
             first_frame_after_activation: true,
         }
     }
 
+    pub fn set_hardware_mode(&mut self, hardware_mode: HardwareMode) {
+        self.hardware_mode = hardware_mode;
+    }
+
+    /// Reads from the vram bank currently selected by VBK
     pub fn read_vram(&self, address: u16) -> u8 {
-        self.v_ram[(address - VRAM_ADDRESS) as usize]
+        self.read_vram_bank(self.vram_bank, address)
     }
 
+    /// Writes to the vram bank currently selected by VBK
     pub fn write_vram(&mut self, address: u16, value: u8) {
-        self.v_ram[(address - VRAM_ADDRESS) as usize] = value;
+        let offset = V_RAM_SIZE * self.vram_bank as usize;
+        self.v_ram[(address - VRAM_ADDRESS) as usize + offset] = value;
+    }
+
+    pub fn read_vram_bank(&self, bank: u8, address: u16) -> u8 {
+        let offset = V_RAM_SIZE * bank as usize;
+        self.v_ram[(address - VRAM_ADDRESS) as usize + offset]
+    }
+
+    pub fn set_vram_bank(&mut self, value: u8) {
+        self.vram_bank = value & 0x01;
+    }
+
+    pub fn get_vram_bank(&self) -> u8 {
+        0xFE | self.vram_bank
+    }
+
+    pub fn set_object_priority_mode(&mut self, value: u8) {
+        self.object_priority_mode = value & 0x01;
+    }
+
+    pub fn get_object_priority_mode(&self) -> u8 {
+        0xFE | self.object_priority_mode
     }
 
     pub fn write_oam(&mut self, address: u16, value: u8) {
@@ -186,6 +238,22 @@ impl Gpu {
         self.step_set_mode();
     }
 
+    /// Clock cycles until the next mode change. None if the LCD is disabled
+    pub fn cycles_until_mode_change(&self) -> Option<u32> {
+        if !self.lcd_enabled {
+            return None;
+        }
+
+        let mode_cycles = match self.stat.mode {
+            Mode::Oam => CYCLES_OAM,
+            Mode::Vram => CYCLES_VRAM,
+            Mode::Hblank => CYCLES_HBLANK,
+            Mode::Vblank => CYCLES_VBLANK,
+        };
+
+        Some(mode_cycles.saturating_sub(self.clock) as u32)
+    }
+
     fn fire_interrupt(&mut self, interrupt: Interrupt) {
         self.interrupts_fired |= interrupt as u8;
     }
@@ -202,6 +270,7 @@ impl Gpu {
                 if self.clock >= CYCLES_VRAM {
                     self.render_scanline_to_screen();
                     self.set_mode(Mode::Hblank);
+                    self.hblank_started = true;
                     self.clock = self.clock % CYCLES_VRAM;
                 }
             }
@@ -269,11 +338,25 @@ impl Gpu {
         }
     }
 
+    /// The DMG blanks the LCD while the cpu is stopped
+    pub fn blank_screen(&mut self) {
+        self.update_dmg_palette();
+        let color = self.dmg_palette.background[0];
+
+        for pixel in self.screen_buffer.chunks_mut(3) {
+            pixel.copy_from_slice(&color);
+        }
+
+        self.screen.draw(&self.screen_buffer);
+    }
+
     fn clear_screen(&mut self) {
         for i in 0..256 * 256 + 256 {
             self.screen_buffer[i] = 0;
             self.bg_priority_map[i] = PriorityFlag::None;
         }
+
+        self.shade_buffer = [0; SCREEN_WIDTH * SCREEN_HEIGHT];
     }
 
     fn render_screen(&mut self) {
@@ -283,12 +366,27 @@ impl Gpu {
             return;
         }
 
-        self.color_map = self.screen.get_palette();
+        if self.hardware_mode == HardwareMode::Sgb {
+            let sgb_buffer = self.sgb.render(&self.shade_buffer);
+            self.screen.draw_sgb(sgb_buffer);
+            return;
+        }
+
+        self.update_dmg_palette();
         self.screen.draw(&self.screen_buffer);
     }
 
+    /// The palette can be changed while the game is running so it is fetched again every frame
+    fn update_dmg_palette(&mut self) {
+        self.dmg_palette = self
+            .screen
+            .get_compatibility_palette()
+            .unwrap_or_else(|| CompatibilityPalette::monochrome(self.screen.get_palette()));
+    }
+
     fn render_scanline_to_screen(&mut self) {
-        if self.lcdc.background_display {
+        //In CGB mode LCDC bit 0 is the master priority and the background is always drawn
+        if self.lcdc.background_display || self.is_cgb() {
             self.render_background_line();
         }
 
@@ -306,7 +404,14 @@ impl Gpu {
             sprite_height = 16;
         }
 
-        for sprite_count in (0..40).rev() {
+        //Sprites drawn later end up on top, so the highest priority sprite is drawn last
+        let mut sprites: Vec<u16> = (0..40).collect();
+        if self.sprites_prioritized_by_x() {
+            //The sort is stable so sprites at the same X keep the OAM order
+            sprites.sort_by_key(|sprite| self.read_oam(OAM_ADDRESS + sprite * 4 + 1));
+        }
+
+        for sprite_count in sprites.into_iter().rev() {
             //Each sprite is consists for 4 bytes
             //0 = Y, 1 = X, 2 = Tile, 3 = Options
             let sprite_begin_address = OAM_ADDRESS + sprite_count * 4;
@@ -319,6 +424,13 @@ impl Gpu {
                 let sprite_tile = self.read_oam(sprite_begin_address + 2);
                 let sprite_options = self.read_oam(sprite_begin_address + 3);
 
+                //CGB sprites can use tiles from vram bank 1
+                let tile_bank = if self.is_cgb() && is_bit_set(&sprite_options, 3) {
+                    1
+                } else {
+                    0
+                };
+
                 let tile_begin_address = TILESET_FIRST_BEGIN_ADDRESS + (sprite_tile as u16 * 16);
 
                 let line_offset = flip_y(&sprite_options, current_line, sprite_height, sprite_y);
@@ -328,8 +440,8 @@ impl Gpu {
                 //The color data sits one byte after the pixel data
                 let tile_color_data_address = tile_begin_address + (line_offset * 2) as u16 + 1;
 
-                let tile_data = self.read_vram(tile_data_address);
-                let tile_color_data = self.read_vram(tile_color_data_address);
+                let tile_data = self.read_vram_bank(tile_bank, tile_data_address);
+                let tile_color_data = self.read_vram_bank(tile_bank, tile_color_data_address);
 
                 for x in 0..8 {
                     let x_offset = sprite_x + x as i16;
@@ -368,36 +480,54 @@ impl Gpu {
                 self.calculate_bgmap_address(y_bgmap, x_bgmap)
             };
 
-            let tile = self.read_vram(tile_address);
+            let tile = self.read_vram_bank(0, tile_address);
+
+            //CGB tile attributes sit at the same address in vram bank 1
+            let attributes = if self.is_cgb() {
+                self.read_vram_bank(1, tile_address)
+            } else {
+                0
+            };
+
+            let tile_bank = if is_bit_set(&attributes, 3) { 1 } else { 0 };
 
             let tile_begin_address = self.calculate_tile_address(tile);
 
             //Each tile consists of one byte at the y axes
-            let y_tile_address_offset = if line_is_window && column_is_window {
-                (self.current_scanline - self.window_y) % 8 * 2
+            let mut tile_line = if line_is_window && column_is_window {
+                (self.current_scanline - self.window_y) % 8
             } else {
-                y_bgmap % 8 * 2
-            } as u16;
+                y_bgmap % 8
+            };
+
+            if is_bit_set(&attributes, 6) {
+                tile_line = 7 - tile_line;
+            }
 
-            let tile_data_address = tile_begin_address + y_tile_address_offset;
+            let tile_data_address = tile_begin_address + tile_line as u16 * 2;
             //The color data sits one byte after the pixel data
             let tile_color_data_address = tile_data_address + 1;
 
-            let tile_data = self.read_vram(tile_data_address);
-            let tile_color_data = self.read_vram(tile_color_data_address);
+            let tile_data = self.read_vram_bank(tile_bank, tile_data_address);
+            let tile_color_data = self.read_vram_bank(tile_bank, tile_color_data_address);
 
-            let pixel_index = if column_is_window && line_is_window {
+            let mut pixel_index = if column_is_window && line_is_window {
                 self.window_x.wrapping_sub(x) % 8
             } else {
                 7 - (x_bgmap % 8)
             };
 
+            if is_bit_set(&attributes, 5) {
+                pixel_index = 7 - pixel_index;
+            }
+
             self.draw_background_pixel(
                 tile_data,
                 tile_color_data,
                 self.current_scanline,
                 x,
                 pixel_index,
+                attributes,
             );
         }
     }
@@ -443,38 +573,47 @@ impl Gpu {
         pixel_index: u8,
         sprite_options: &u8,
     ) {
-        let sprite_palette = if is_bit_set(&sprite_options, 4) {
-            &self.sprite_palette1
-        } else {
-            &self.sprite_palette0
-        };
-
         let color_index = get_color_index(tile_data, tile_color_data, pixel_index);
         //Color 0 is transparent for sprites
         if color_index == 0 {
             return;
         }
 
-        let pixel = sprite_palette[color_index as usize];
-
         let offset = y as usize + 256 * x as usize;
 
         if self.background_has_priority_over_pixel(sprite_options, offset) {
             return;
         }
 
-        self.draw_pixel_to_buffer(x as usize, y as usize, self.color_map[pixel as usize]);
+        let rgb = if self.is_cgb() {
+            self.object_palette
+                .get_color(sprite_options & 0x07, color_index)
+        } else {
+            let (sprite_palette, colors) = if is_bit_set(&sprite_options, 4) {
+                (&self.sprite_palette1, &self.dmg_palette.object1)
+            } else {
+                (&self.sprite_palette0, &self.dmg_palette.object0)
+            };
+
+            let shade = sprite_palette[color_index as usize];
+            self.shade_buffer[y as usize * SCREEN_WIDTH + x as usize] = shade;
+            colors[shade as usize]
+        };
+
+        self.draw_pixel_to_buffer(x as usize, y as usize, rgb);
     }
 
     fn background_has_priority_over_pixel(&self, sprite_options: &u8, offset: usize) -> bool {
-        if !is_bit_set(&sprite_options, 7) {
+        //With the CGB master priority cleared sprites are always on top
+        if self.is_cgb() && !self.lcdc.background_display {
             return false;
         }
 
         //Sprite will only be behind colors 1-3
         match self.bg_priority_map[offset] {
             PriorityFlag::Color0 => false,
-            PriorityFlag::None => true,
+            PriorityFlag::BackgroundPriority => true,
+            PriorityFlag::None => is_bit_set(&sprite_options, 7),
         }
     }
 
@@ -485,17 +624,37 @@ impl Gpu {
         y: u8,
         x: u8,
         pixel_index: u8,
+        attributes: u8,
     ) {
         let color_index = get_color_index(tile_data, tile_color_data, pixel_index);
-        let pixel = self.bg_pal[color_index as usize];
         let offset = y as usize + 256 * x as usize;
 
         //Set priority information for sprites. Sprite will never be behind color 0
         if color_index == 0 {
             self.bg_priority_map[offset] = PriorityFlag::Color0
+        } else if is_bit_set(&attributes, 7) {
+            self.bg_priority_map[offset] = PriorityFlag::BackgroundPriority
         }
 
-        self.draw_pixel_to_buffer(x as usize, y as usize, self.color_map[pixel as usize]);
+        let rgb = if self.is_cgb() {
+            self.background_palette
+                .get_color(attributes & 0x07, color_index)
+        } else {
+            let shade = self.bg_pal[color_index as usize];
+            self.shade_buffer[y as usize * SCREEN_WIDTH + x as usize] = shade;
+            self.dmg_palette.background[shade as usize]
+        };
+
+        self.draw_pixel_to_buffer(x as usize, y as usize, rgb);
+    }
+
+    fn is_cgb(&self) -> bool {
+        self.hardware_mode == HardwareMode::Cgb
+    }
+
+    /// The DMG prioritizes sprites with a smaller X position, the CGB uses the OAM order unless OPRI is set
+    fn sprites_prioritized_by_x(&self) -> bool {
+        !self.is_cgb() || self.object_priority_mode == 1
     }
 
     fn draw_pixel_to_buffer(&mut self, x: usize, y: usize, rgb: [u8; 3]) {
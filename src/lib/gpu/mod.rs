@@ -1,11 +1,19 @@
+pub mod cgb_palette;
+pub mod compatibility_palette;
 pub mod gpu;
 pub mod lcdc;
 pub mod stat;
 
+use compatibility_palette::CompatibilityPalette;
+
 pub const SCREEN_WIDTH: usize = 160;
 pub const SCREEN_HEIGHT: usize = 144;
 pub const SCALE: u8 = 4;
 pub const BUFFER_SIZE: usize = SCREEN_WIDTH * SCREEN_HEIGHT * 3;
+//The SGB draws a border around the game screen
+pub const SGB_SCREEN_WIDTH: usize = 256;
+pub const SGB_SCREEN_HEIGHT: usize = 224;
+pub const SGB_BUFFER_SIZE: usize = SGB_SCREEN_WIDTH * SGB_SCREEN_HEIGHT * 3;
 
 #[derive(Clone, Copy)]
 pub enum Pixel {
@@ -17,5 +25,8 @@ pub enum Pixel {
 
 pub trait Screen {
     fn draw(&self, screen_buffer: &[u8; BUFFER_SIZE]);
+    fn draw_sgb(&self, screen_buffer: &[u8; SGB_BUFFER_SIZE]);
     fn get_palette(&self) -> [[u8; 3]; 4];
+    /// Separate colors for background and sprites of a DMG game. None uses get_palette for everything
+    fn get_compatibility_palette(&self) -> Option<CompatibilityPalette>;
 }
@@ -55,16 +55,19 @@ impl Joypad {
     }
 
     pub fn read_input(&self, value: u8) -> u8 {
+        //A pressed key pulls its line low. Lines of unselected groups stay high
+        let mut input = 0x0F;
+
         //Bit 4 = Direction keys selected
         if !is_bit_set(&value, 4) {
-            return self.direction_key_status;
+            input &= self.direction_key_status;
         }
 
         //Bit 5 = Button keys
         if !is_bit_set(&value, 5) {
-            return self.button_key_status;
+            input &= self.button_key_status;
         }
 
-        return 0;
+        0xC0 | (value & 0x30) | (input & 0x0F)
     }
 }
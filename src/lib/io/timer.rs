@@ -1,83 +1,192 @@
 use crate::lib::memory::interrupts::Interrupt;
 use crate::lib::util::binary::is_bit_set;
 
-const DIVIDER_CYCLES: u32 = 256;
-const SPEED_0_CYCLES: u32 = 1024;
-const SPEED_1_CYCLES: u32 = 16;
-const SPEED_2_CYCLES: u32 = 64;
-const SPEED_3_CYCLES: u32 = 256;
+//Bit of the system counter the timer listens to for each speed selected in TAC
+const SPEED_0_BIT: u16 = 1 << 9;
+const SPEED_1_BIT: u16 = 1 << 3;
+const SPEED_2_BIT: u16 = 1 << 5;
+const SPEED_3_BIT: u16 = 1 << 7;
+
+//Bit 4 of DIV clocks the frame sequencer of the apu. Bit 5 in double speed mode
+const FRAME_SEQUENCER_BIT: u16 = 1 << 12;
+const FRAME_SEQUENCER_BIT_DOUBLE_SPEED: u16 = 1 << 13;
+
+//Value of the system counter after the boot rom has finished on DMG
+const SYSTEM_COUNTER_AFTER_BOOT: u16 = 0xABCC;
+
+#[derive(PartialEq)]
+enum ReloadState {
+    None,
+    //TIMA has overflowed and reads 0 for one M-cycle
+    Overflowed,
+    //TMA has just been copied into TIMA
+    Reloading,
+}
 
 pub struct Timer {
-    pub divider: u8,
     pub counter: u8,
     pub modulo: u8,
     pub timer_control: u8,
     pub interrupts_fired: u8,
-    clock_cycles_divider: u32,
-    clock_cycles_timer: u32,
-    has_overflowed: bool,
+    pub frame_sequencer_clocked: bool,
+    system_counter: u16,
+    reload_state: ReloadState,
+    double_speed: bool,
 }
 
 impl Timer {
     pub fn new() -> Timer {
         Timer {
-            divider: 0,
             counter: 0,
             modulo: 0,
             timer_control: 0,
             interrupts_fired: 0,
-            clock_cycles_divider: 0,
-            clock_cycles_timer: 0,
-            has_overflowed: false,
+            frame_sequencer_clocked: false,
+            system_counter: SYSTEM_COUNTER_AFTER_BOOT,
+            reload_state: ReloadState::None,
+            double_speed: false,
         }
     }
 
     pub fn step(&mut self, clock_cycles: u8) {
-        let cycles = clock_cycles as u32;
-        self.clock_cycles_divider += cycles;
+        for _ in 0..clock_cycles / 4 {
+            self.step_machine_cycle();
+        }
+    }
 
-        while self.clock_cycles_divider >= DIVIDER_CYCLES {
-            self.divider = self.divider.wrapping_add(1);
-            self.clock_cycles_divider -= DIVIDER_CYCLES;
+    fn step_machine_cycle(&mut self) {
+        match self.reload_state {
+            ReloadState::Overflowed => {
+                //Reload and interrupt happen one M-cycle after the overflow
+                self.counter = self.modulo;
+                self.fire_interrupt(Interrupt::Timer);
+                self.reload_state = ReloadState::Reloading;
+            }
+            ReloadState::Reloading => self.reload_state = ReloadState::None,
+            ReloadState::None => {}
         }
 
-        let speed = self.timer_control << 6;
-        let running = is_bit_set(&self.timer_control, 2);
+        self.set_system_counter(self.system_counter.wrapping_add(4));
+    }
 
-        if !running {
-            return;
+    pub fn get_divider(&self) -> u8 {
+        (self.system_counter >> 8) as u8
+    }
+
+    /// Writing any value to DIV resets the whole system counter which can cause a falling edge
+    pub fn reset_divider(&mut self) {
+        self.set_system_counter(0);
+    }
+
+    pub fn set_counter(&mut self, value: u8) {
+        match self.reload_state {
+            //Writing TIMA in the cycle after the overflow cancels the reload and the interrupt
+            ReloadState::Overflowed => {
+                self.counter = value;
+                self.reload_state = ReloadState::None;
+            }
+            //TMA is written to TIMA in this cycle so the write is ignored
+            ReloadState::Reloading => {}
+            ReloadState::None => self.counter = value,
+        }
+    }
+
+    pub fn set_modulo(&mut self, value: u8) {
+        self.modulo = value;
+
+        //TMA is copied to TIMA during the reload cycle so the new value gets copied as well
+        if self.reload_state == ReloadState::Reloading {
+            self.counter = value;
         }
+    }
 
-        self.clock_cycles_timer += cycles;
+    pub fn get_timer_control(&self) -> u8 {
+        self.timer_control | 0xF8
+    }
 
-        let timer_cycles = match speed {
-            0 => SPEED_0_CYCLES,
-            0x40 => SPEED_1_CYCLES,
-            0x80 => SPEED_2_CYCLES,
-            0xC0 => SPEED_3_CYCLES,
-            _ => panic!("Unknown timer speed: 0x{:X}", speed),
-        };
+    pub fn set_timer_control(&mut self, value: u8) {
+        let old_signal = self.timer_signal(self.system_counter);
+        self.timer_control = value & 0x07;
 
-        if self.has_overflowed {
-            self.fire_interrupt(Interrupt::Timer);
-            self.counter = self.modulo;
-            self.has_overflowed = false;
+        //Disabling the timer or switching the selected bit can cause a falling edge as well
+        if old_signal && !self.timer_signal(self.system_counter) {
+            self.increment_counter();
         }
+    }
 
-        //It could happen that the timer needs to be incremented multiple times within a given cycle
-        while self.clock_cycles_timer >= timer_cycles {
-            if self.counter == 255 {
-                //Overflow does not happen immediately
-                self.has_overflowed = true;
-                self.counter = 0;
-                return;
-            }
+    fn set_system_counter(&mut self, value: u16) {
+        let old_value = self.system_counter;
+        self.system_counter = value;
+
+        if self.timer_signal(old_value) && !self.timer_signal(value) {
+            self.increment_counter();
+        }
 
-            self.counter += 1;
-            self.clock_cycles_timer -= timer_cycles;
+        let frame_sequencer_bit = self.frame_sequencer_bit();
+        if old_value & frame_sequencer_bit != 0 && value & frame_sequencer_bit == 0 {
+            self.frame_sequencer_clocked = true;
         }
     }
 
+    /// The timer runs at cpu speed so the frame sequencer uses a higher bit to stay at 512 Hz
+    pub fn set_double_speed(&mut self, double_speed: bool) {
+        self.double_speed = double_speed;
+    }
+
+    fn frame_sequencer_bit(&self) -> u16 {
+        if self.double_speed {
+            FRAME_SEQUENCER_BIT_DOUBLE_SPEED
+        } else {
+            FRAME_SEQUENCER_BIT
+        }
+    }
+
+    /// Clock cycles until TIMA overflows or gets reloaded. None if the timer is stopped
+    pub fn cycles_until_overflow(&self) -> Option<u32> {
+        if self.reload_state != ReloadState::None {
+            return Some(4);
+        }
+
+        if !self.is_running() {
+            return None;
+        }
+
+        //A falling edge of the selected bit happens every time the counter wraps below the next bit
+        let period = self.selected_bit() as u32 * 2;
+        let cycles_to_edge = period - (self.system_counter as u32 & (period - 1));
+
+        Some(cycles_to_edge + (0xFF - self.counter as u32) * period)
+    }
+
+    /// TIMA is incremented on the falling edge of the selected system counter bit ANDed with the enable bit
+    fn timer_signal(&self, system_counter: u16) -> bool {
+        self.is_running() && system_counter & self.selected_bit() != 0
+    }
+
+    fn is_running(&self) -> bool {
+        is_bit_set(&self.timer_control, 2)
+    }
+
+    fn selected_bit(&self) -> u16 {
+        match self.timer_control & 0x03 {
+            0 => SPEED_0_BIT,
+            1 => SPEED_1_BIT,
+            2 => SPEED_2_BIT,
+            _ => SPEED_3_BIT,
+        }
+    }
+
+    fn increment_counter(&mut self) {
+        if self.counter == 0xFF {
+            //Overflow does not reload TIMA immediately
+            self.counter = 0;
+            self.reload_state = ReloadState::Overflowed;
+            return;
+        }
+
+        self.counter += 1;
+    }
+
     fn fire_interrupt(&mut self, interrupt: Interrupt) {
         self.interrupts_fired |= interrupt as u8;
     }
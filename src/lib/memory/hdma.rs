@@ -0,0 +1,69 @@
+pub const HDMA_BLOCK_SIZE: u16 = 16;
+
+/// State of the CGB vram DMA (HDMA1-HDMA5). Transfers happen in blocks of 16 bytes
+pub struct Hdma {
+    pub source: u16,
+    pub destination: u16,
+    pub hblank_active: bool,
+    //Remaining blocks - 1 as reported in HDMA5
+    length: u8,
+}
+
+impl Default for Hdma {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Hdma {
+    pub fn new() -> Hdma {
+        Hdma {
+            source: 0,
+            destination: 0,
+            hblank_active: false,
+            length: 0x7F,
+        }
+    }
+
+    pub fn set_source_high(&mut self, value: u8) {
+        self.source = (self.source & 0x00FF) | (value as u16) << 8;
+    }
+
+    /// The lower 4 bits are ignored
+    pub fn set_source_low(&mut self, value: u8) {
+        self.source = (self.source & 0xFF00) | (value & 0xF0) as u16;
+    }
+
+    /// Only bits 12-4 are used. The destination is always in vram
+    pub fn set_destination_high(&mut self, value: u8) {
+        self.destination = (self.destination & 0x00FF) | ((value & 0x1F) as u16) << 8;
+    }
+
+    pub fn set_destination_low(&mut self, value: u8) {
+        self.destination = (self.destination & 0xFF00) | (value & 0xF0) as u16;
+    }
+
+    /// Bit 7 is cleared while a H-Blank DMA is active. Reads 0xFF once a transfer is done
+    pub fn get_control(&self) -> u8 {
+        let inactive = if self.hblank_active { 0 } else { 0x80 };
+        inactive | self.length
+    }
+
+    pub fn set_length(&mut self, value: u8) {
+        self.length = value & 0x7F;
+    }
+
+    /// Advances source and destination by one block. Returns true if the transfer is complete
+    pub fn finish_block(&mut self) -> bool {
+        self.source = self.source.wrapping_add(HDMA_BLOCK_SIZE);
+        self.destination = (self.destination + HDMA_BLOCK_SIZE) & 0x1FF0;
+        self.length = self.length.wrapping_sub(1) & 0x7F;
+
+        if self.length == 0x7F {
+            self.hblank_active = false;
+            return true;
+        }
+
+        false
+    }
+}
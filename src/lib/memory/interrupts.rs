@@ -10,6 +10,27 @@ pub enum Interrupt {
     Joypad = 0x10,
 }
 
+//Ordered by priority
+const INTERRUPTS: [Interrupt; 5] = [
+    Interrupt::Vblank,
+    Interrupt::LcdStat,
+    Interrupt::Timer,
+    Interrupt::Serial,
+    Interrupt::Joypad,
+];
+
+impl Interrupt {
+    pub fn isr_address(&self) -> u16 {
+        match self {
+            Interrupt::Vblank => 0x0040,
+            Interrupt::LcdStat => 0x0048,
+            Interrupt::Timer => 0x0050,
+            Interrupt::Serial => 0x0058,
+            Interrupt::Joypad => 0x0060,
+        }
+    }
+}
+
 pub struct InterruptState {
     pub interrupt_flags: u8,
     pub interrupts_enabled: u8,
@@ -34,6 +55,14 @@ impl InterruptState {
             && self.interrupts_enabled & interrupt_value == interrupt_value
     }
 
+    /// Returns the requested and enabled interrupt with the highest priority
+    pub fn pending_interrupt(&self) -> Option<Interrupt> {
+        INTERRUPTS
+            .iter()
+            .find(|interrupt| self.interrupt_fired(interrupt))
+            .copied()
+    }
+
     pub fn reset_interrupt(&mut self, interrupt: &Interrupt) {
         self.interrupt_flags &= *interrupt as u8 ^ 0xFF;
     }
@@ -1,8 +1,13 @@
 use crate::lib::apu::apu::Apu;
-use crate::lib::cartridge::Cartridge;
+use crate::lib::cartridge::{
+    Cartridge, CGB_FLAG_ADDRESS, OLD_LICENSEE_CODE_ADDRESS, SGB_FLAG_ADDRESS,
+};
+use crate::lib::emulation::HardwareMode;
 use crate::lib::gpu::gpu::Gpu;
+use crate::lib::gpu::stat::Mode;
 use crate::lib::io::joypad::Joypad;
 use crate::lib::io::timer::Timer;
+use crate::lib::memory::hdma::{Hdma, HDMA_BLOCK_SIZE};
 use crate::lib::memory::interrupts;
 use crate::lib::memory::interrupts::InterruptState;
 use crate::lib::util::binary;
@@ -14,7 +19,14 @@ pub const H_RAM_ADDR: u16 = 0xFF80;
 pub const VRAM_ADDRESS: u16 = 0x8000;
 pub const OAM_ADDRESS: u16 = 0xFE00;
 
-const W_RAM_SIZE: usize = 8192;
+//Upper bound for fast forwarding a halted cpu so the emulation loop stays responsive
+const MAX_IDLE_CYCLES: u32 = 252;
+
+//Copying one block of 16 bytes stalls the cpu for 8 M-cycles
+const HDMA_BLOCK_CYCLES: u8 = 32;
+
+const W_RAM_BANK_SIZE: usize = 4096;
+const W_RAM_BANKS: usize = 8;
 const H_RAM_SIZE: usize = 127;
 
 pub enum Opcode {
@@ -27,11 +39,18 @@ pub struct Mmu<'a> {
     pub timer: Timer,
     pub interrupts: InterruptState,
     pub apu: &'a mut Apu<'a>,
-    w_ram: [u8; W_RAM_SIZE],
+    pub hardware_mode: HardwareMode,
+    w_ram: [u8; W_RAM_BANK_SIZE * W_RAM_BANKS],
+    w_ram_bank: u8,
+    speed_switch: u8,
+    infrared: u8,
+    undocumented_registers: [u8; 4],
     h_ram: [u8; H_RAM_SIZE],
     joypad_select: u8,
     joypad: u8,
     cartridge: &'a mut dyn Cartridge,
+    hdma: Hdma,
+    cycles_ticked: u32,
 }
 
 impl<'a> Mmu<'a> {
@@ -40,38 +59,162 @@ impl<'a> Mmu<'a> {
         gpu: &'a mut Gpu,
         apu: &'a mut Apu<'a>,
     ) -> Mmu<'a> {
+        let hardware_mode = HardwareMode::from_header(
+            cartridge.read(CGB_FLAG_ADDRESS as u16),
+            cartridge.read(SGB_FLAG_ADDRESS as u16),
+            cartridge.read(OLD_LICENSEE_CODE_ADDRESS as u16),
+        );
+        gpu.set_hardware_mode(hardware_mode);
+
         Mmu {
             gpu,
             timer: Timer::new(),
             interrupts: InterruptState::new(),
             apu,
-            w_ram: [0; W_RAM_SIZE],
+            hardware_mode,
+            w_ram: [0; W_RAM_BANK_SIZE * W_RAM_BANKS],
+            w_ram_bank: 1,
+            speed_switch: 0,
+            infrared: 0,
+            undocumented_registers: [0; 4],
             h_ram: [0; H_RAM_SIZE],
             joypad_select: 0xFF,
             joypad: 0xFF,
             cartridge,
+            hdma: Hdma::new(),
+            cycles_ticked: 0,
         }
     }
 
-    pub fn step(&mut self, joypad: &Joypad, clock_cycles: u8) {
-        self.read_joypad(joypad);
-        self.gpu.step(clock_cycles);
+    /// Advances every component besides the cpu by the given amount of clock cycles.
+    /// The cpu calls this for every memory access so reads and writes land on the correct M-cycle
+    pub fn tick(&mut self, clock_cycles: u8) {
+        //In double speed mode only the cpu and the timer run twice as fast
+        let normal_speed_cycles = self.normal_speed_cycles(clock_cycles);
+
+        self.cycles_ticked += clock_cycles as u32;
+        self.gpu.step(normal_speed_cycles);
         self.timer.step(clock_cycles);
+        //The RTC crystal is not affected by double speed
+        self.cartridge.tick(normal_speed_cycles as u32);
+
+        if self.timer.frame_sequencer_clocked {
+            self.apu.clock_frame_sequencer();
+            self.timer.frame_sequencer_clocked = false;
+        }
+
         // TODO: find a better way to disable audio
         // comment out below to kill audio
-        self.apu.step(clock_cycles);
+        self.apu.step(normal_speed_cycles);
         self.interrupts.interrupt_flags |= self.timer.interrupts_fired;
         self.interrupts.interrupt_flags |= self.gpu.interrupts_fired;
         self.gpu.interrupts_fired = 0;
         self.timer.interrupts_fired = 0;
+
+        if self.gpu.hblank_started {
+            self.gpu.hblank_started = false;
+
+            if self.hdma.hblank_active {
+                self.hdma_transfer_block();
+            }
+        }
     }
 
-    pub fn save(&self) {
-        self.cartridge.dump_savegame();
+    /// Converts cpu clock cycles to the clock cycles seen by the gpu and apu
+    fn normal_speed_cycles(&self, clock_cycles: u8) -> u8 {
+        if self.is_double_speed() {
+            clock_cycles / 2
+        } else {
+            clock_cycles
+        }
+    }
+
+    /// Clock cycles until the next component could request an interrupt.
+    /// A halted cpu skips straight ahead by this amount instead of ticking every M-cycle
+    pub fn cycles_until_next_event(&self) -> u8 {
+        let speed_factor = if self.is_double_speed() { 2 } else { 1 };
+        let cycles = [
+            self.timer.cycles_until_overflow(),
+            self.gpu
+                .cycles_until_mode_change()
+                .map(|cycles| cycles * speed_factor),
+        ]
+        .iter()
+        .flatten()
+        .fold(MAX_IDLE_CYCLES, |min, &cycles| min.min(cycles));
+
+        //Always advance by whole M-cycles
+        ((cycles.max(4) + 3) & !3) as u8
     }
 
-    fn read_joypad(&mut self, joypad: &Joypad) {
-        self.joypad = joypad.read_input(self.joypad_select);
+    /// Used while the cpu is stopped. Timer, gpu and apu are frozen until a joypad line goes low
+    pub fn tick_stopped(&mut self) {
+        let cycles = MAX_IDLE_CYCLES as u8;
+        self.cycles_ticked += cycles as u32;
+        self.apu.step_silent(self.normal_speed_cycles(cycles));
+        self.cartridge.tick(self.normal_speed_cycles(cycles) as u32);
+    }
+
+    /// STOP resets DIV and blanks the LCD
+    pub fn enter_stop_mode(&mut self) {
+        self.timer.reset_divider();
+        self.gpu.blank_screen();
+    }
+
+    /// STOP switches the speed instead of stopping the cpu if the switch was prepared in KEY1
+    pub fn speed_switch_prepared(&self) -> bool {
+        self.hardware_mode == HardwareMode::Cgb && self.speed_switch & 0x01 == 0x01
+    }
+
+    pub fn switch_speed(&mut self) {
+        self.speed_switch = (self.speed_switch ^ 0x80) & 0x80;
+        self.timer.set_double_speed(self.is_double_speed());
+        self.timer.reset_divider();
+    }
+
+    /// Bit 7 of KEY1 is set while the cpu runs at 8 MHz
+    pub fn is_double_speed(&self) -> bool {
+        self.speed_switch & 0x80 == 0x80
+    }
+
+    /// True if any selected joypad line is low which wakes the cpu from STOP
+    pub fn joypad_line_low(&self) -> bool {
+        self.joypad & 0x0F != 0x0F
+    }
+
+    /// Returns the clock cycles ticked since the last call and resets the counter
+    pub fn take_cycles_ticked(&mut self) -> u32 {
+        let cycles = self.cycles_ticked;
+        self.cycles_ticked = 0;
+        cycles
+    }
+
+    pub fn cycles_ticked(&self) -> u32 {
+        self.cycles_ticked
+    }
+
+    /// Runs a SGB game like on a DMG
+    pub fn disable_sgb(&mut self) {
+        if self.hardware_mode == HardwareMode::Sgb {
+            self.hardware_mode = HardwareMode::Dmg;
+            self.gpu.set_hardware_mode(HardwareMode::Dmg);
+        }
+    }
+
+    /// Gives access to the save data and state of the cartridge while running
+    pub fn cartridge(&self) -> &dyn Cartridge {
+        &*self.cartridge
+    }
+
+    pub fn cartridge_mut(&mut self) -> &mut dyn Cartridge {
+        &mut *self.cartridge
+    }
+
+    pub fn read_joypad(&mut self, joypad: &Joypad) {
+        self.joypad = match self.hardware_mode {
+            HardwareMode::Sgb => self.gpu.sgb.read_joypad(joypad),
+            _ => joypad.read_input(self.joypad_select),
+        };
     }
 
     fn dma_transfer(&mut self, source_address: u8) {
@@ -83,25 +226,82 @@ impl<'a> Mmu<'a> {
         let start_address: u16 = (source_address as u16) << 8;
 
         for offset in 0..160 {
+            self.gpu.write_oam(
+                OAM_ADDRESS + offset,
+                self.read_direct(start_address + offset),
+            )
+        }
+    }
+
+    /// H-Blank DMA copies one block per H-Blank. General purpose DMA copies everything at once
+    /// while the cpu is stalled
+    fn start_hdma(&mut self, value: u8) {
+        //Writing bit 7 = 0 while a H-Blank DMA is active cancels it
+        if self.hdma.hblank_active && value & 0x80 == 0 {
+            self.hdma.hblank_active = false;
+            return;
+        }
+
+        self.hdma.set_length(value);
+
+        if value & 0x80 == 0x80 {
+            self.hdma.hblank_active = true;
+            //Without a H-Blank edge to wait for the first block is copied right away
+            let in_hblank = matches!(self.gpu.stat.mode, Mode::Hblank);
+            if !self.gpu.lcdc.display_enabled || in_hblank {
+                self.hdma_transfer_block();
+            }
+            return;
+        }
+
+        while !self.hdma_transfer_block() {}
+    }
+
+    /// Copies one block of 16 bytes to vram. Returns true if the transfer is complete
+    fn hdma_transfer_block(&mut self) -> bool {
+        for offset in 0..HDMA_BLOCK_SIZE {
+            let value = self.read_direct(self.hdma.source.wrapping_add(offset));
             self.gpu
-                .write_oam(OAM_ADDRESS + offset, self.read(start_address + offset))
+                .write_vram(VRAM_ADDRESS + self.hdma.destination + offset, value);
         }
+
+        //The transfer takes the same time in double speed mode which stalls the cpu twice as long
+        let stall_cycles = if self.is_double_speed() {
+            HDMA_BLOCK_CYCLES * 2
+        } else {
+            HDMA_BLOCK_CYCLES
+        };
+        self.tick(stall_cycles);
+        self.hdma.finish_block()
     }
 
-    pub fn read(&self, address: u16) -> u8 {
+    /// Timed read as performed by the cpu. Takes one M-cycle
+    pub fn read(&mut self, address: u16) -> u8 {
+        self.tick(4);
+        self.read_direct(address)
+    }
+
+    /// Timed write as performed by the cpu. Takes one M-cycle
+    pub fn write(&mut self, address: u16, value: u8) {
+        self.tick(4);
+        self.write_direct(address, value);
+    }
+
+    /// Reads without advancing the clock
+    pub fn read_direct(&self, address: u16) -> u8 {
         match address {
-            W_RAM_ADDRESS..=0xDFFF => self.w_ram[(address - W_RAM_ADDRESS) as usize],
-            ECHO_RAM_ADDRESS..=0xFDFF => self.w_ram[(address - ECHO_RAM_ADDRESS) as usize],
+            W_RAM_ADDRESS..=0xDFFF => self.w_ram[self.w_ram_offset(address - W_RAM_ADDRESS)],
+            ECHO_RAM_ADDRESS..=0xFDFF => self.w_ram[self.w_ram_offset(address - ECHO_RAM_ADDRESS)],
             0..=0x7FFF => self.cartridge.read(address),
             interrupts::INTERRUPT_FLAGS_ADDRESS => self.interrupts.interrupt_flags,
             VRAM_ADDRESS..=0x9FFF => self.gpu.read_vram(address),
             OAM_ADDRESS..=0xFE9F => self.gpu.read_oam(address),
             EXT_RAM_START_ADDRESS..=0xBFFF => self.cartridge.read_ram(address),
             0xFF00 => self.joypad,
-            0xFF04 => self.timer.divider,
+            0xFF04 => self.timer.get_divider(),
             0xFF05 => self.timer.counter,
             0xFF06 => self.timer.modulo,
-            0xFF07 => self.timer.timer_control,
+            0xFF07 => self.timer.get_timer_control(),
             0xFF10..=0xFF3F => self.apu.read(address),
             0xFF40 => self.gpu.get_lcdc(),
             0xFF41 => self.gpu.get_stat(),
@@ -114,24 +314,43 @@ impl<'a> Mmu<'a> {
             0xFF49 => self.gpu.get_sprite_palette1(),
             0xFF4A => self.gpu.window_y,
             0xFF4B => self.gpu.window_x,
+            0xFF4D | 0xFF4F | 0xFF51..=0xFF56 | 0xFF68..=0xFF6C | 0xFF70 | 0xFF72..=0xFF77 => {
+                self.read_cgb_register(address)
+            }
             H_RAM_ADDR..=0xFFFE => self.h_ram[(address - H_RAM_ADDR) as usize],
             interrupts::INTERRUPT_ENABLE_ADDRESS => self.interrupts.interrupts_enabled,
-            _ => 0,
+            //Unmapped addresses read as open bus
+            _ => 0xFF,
         }
     }
 
-    pub fn write(&mut self, address: u16, value: u8) {
+    /// Writes without advancing the clock
+    pub fn write_direct(&mut self, address: u16, value: u8) {
         match address {
-            W_RAM_ADDRESS..=0xDFFF => self.w_ram[(address - W_RAM_ADDRESS) as usize] = value,
+            W_RAM_ADDRESS..=0xDFFF => {
+                let offset = self.w_ram_offset(address - W_RAM_ADDRESS);
+                self.w_ram[offset] = value
+            }
+            ECHO_RAM_ADDRESS..=0xFDFF => {
+                let offset = self.w_ram_offset(address - ECHO_RAM_ADDRESS);
+                self.w_ram[offset] = value
+            }
             EXT_RAM_START_ADDRESS..=0xBFFF => self.cartridge.write_ram(address, value),
             0..=0x7FFF => self.cartridge.write(address, value),
             interrupts::INTERRUPT_FLAGS_ADDRESS => self.interrupts.interrupt_flags = value,
             interrupts::INTERRUPT_ENABLE_ADDRESS => self.interrupts.interrupts_enabled = value,
-            0xFF00 => self.joypad_select = value,
-            0xFF04 => self.timer.divider = 0,
-            0xFF05 => self.timer.counter = value,
-            0xFF06 => self.timer.modulo = value,
-            0xFF07 => self.timer.timer_control = value,
+            0xFF00 => {
+                self.joypad_select = value;
+
+                //The SGB receives command packets through P14 and P15
+                if self.hardware_mode == HardwareMode::Sgb {
+                    self.gpu.sgb.write_joypad(value);
+                }
+            }
+            0xFF04 => self.timer.reset_divider(),
+            0xFF05 => self.timer.set_counter(value),
+            0xFF06 => self.timer.set_modulo(value),
+            0xFF07 => self.timer.set_timer_control(value),
             0xFF10..=0xFF3F => self.apu.write(address, value),
             0xFF40 => self.gpu.set_lcdc(value),
             0xFF41 => self.gpu.set_stat(value),
@@ -150,6 +369,9 @@ impl<'a> Mmu<'a> {
                 }
                 self.gpu.window_x = value
             }
+            0xFF4D | 0xFF4F | 0xFF51..=0xFF56 | 0xFF68..=0xFF6C | 0xFF70 | 0xFF72..=0xFF77 => {
+                self.write_cgb_register(address, value)
+            }
             H_RAM_ADDR..=0xFFFE => self.h_ram[(address - H_RAM_ADDR) as usize] = value,
             VRAM_ADDRESS..=0x9FFF => self.gpu.write_vram(address, value),
             OAM_ADDRESS..=0xFE9F => self.gpu.write_oam(address, value),
@@ -157,16 +379,82 @@ impl<'a> Mmu<'a> {
         }
     }
 
+    /// 0xC000-0xCFFF is always bank 0, 0xD000-0xDFFF is switched by SVBK in CGB mode
+    fn w_ram_offset(&self, offset: u16) -> usize {
+        let offset = offset as usize;
+
+        if offset < W_RAM_BANK_SIZE {
+            return offset;
+        }
+
+        (offset - W_RAM_BANK_SIZE) + W_RAM_BANK_SIZE * self.w_ram_bank as usize
+    }
+
+    fn read_cgb_register(&self, address: u16) -> u8 {
+        if self.hardware_mode != HardwareMode::Cgb {
+            return 0xFF;
+        }
+
+        match address {
+            0xFF4D => 0x7E | self.speed_switch,
+            0xFF4F => self.gpu.get_vram_bank(),
+            //The source and destination registers are write only
+            0xFF51..=0xFF54 => 0xFF,
+            0xFF55 => self.hdma.get_control(),
+            //Bit 1 reads 1 while no infrared light is received
+            0xFF56 => 0x3E | (self.infrared & 0xC1),
+            0xFF68 => self.gpu.background_palette.get_specification(),
+            0xFF69 => self.gpu.background_palette.read_data(),
+            0xFF6A => self.gpu.object_palette.get_specification(),
+            0xFF6B => self.gpu.object_palette.read_data(),
+            0xFF6C => self.gpu.get_object_priority_mode(),
+            0xFF70 => 0xF8 | self.w_ram_bank,
+            0xFF72..=0xFF74 => self.undocumented_registers[(address - 0xFF72) as usize],
+            0xFF75 => 0x8F | self.undocumented_registers[3],
+            //PCM12 and PCM34 expose the digital channel outputs which are not emulated
+            _ => 0x00,
+        }
+    }
+
+    fn write_cgb_register(&mut self, address: u16, value: u8) {
+        if self.hardware_mode != HardwareMode::Cgb {
+            return;
+        }
+
+        match address {
+            //Only the prepare bit is writable. Bit 7 reports the current speed
+            0xFF4D => self.speed_switch = (self.speed_switch & 0x80) | (value & 0x01),
+            0xFF4F => self.gpu.set_vram_bank(value),
+            0xFF51 => self.hdma.set_source_high(value),
+            0xFF52 => self.hdma.set_source_low(value),
+            0xFF53 => self.hdma.set_destination_high(value),
+            0xFF54 => self.hdma.set_destination_low(value),
+            0xFF55 => self.start_hdma(value),
+            0xFF56 => self.infrared = value & 0xC1,
+            0xFF68 => self.gpu.background_palette.set_specification(value),
+            0xFF69 => self.gpu.background_palette.write_data(value),
+            0xFF6A => self.gpu.object_palette.set_specification(value),
+            0xFF6B => self.gpu.object_palette.write_data(value),
+            0xFF6C => self.gpu.set_object_priority_mode(value),
+            //Writing bank 0 selects bank 1
+            0xFF70 => self.w_ram_bank = (value & 0x07).max(1),
+            0xFF72..=0xFF74 => self.undocumented_registers[(address - 0xFF72) as usize] = value,
+            0xFF75 => self.undocumented_registers[3] = value & 0x70,
+            _ => {}
+        }
+    }
+
     pub fn write_word(&mut self, address: u16, value: u16) {
         self.write(address, value as u8);
         self.write(address + 1, (value >> 8) as u8);
     }
 
-    pub fn read_word(&self, address: u16) -> u16 {
-        binary::bytes_to_word(self.read(address + 1), self.read(address))
+    pub fn read_word(&mut self, address: u16) -> u16 {
+        let low = self.read(address);
+        binary::bytes_to_word(self.read(address + 1), low)
     }
 
-    pub fn read_opcode(&self, pc: u16) -> Opcode {
+    pub fn read_opcode(&mut self, pc: u16) -> Opcode {
         let op_code = self.read(pc);
 
         match op_code {
@@ -1,2 +1,3 @@
+pub mod hdma;
 pub mod interrupts;
 pub mod mmu;
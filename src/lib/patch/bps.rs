@@ -0,0 +1,166 @@
+use crate::lib::patch::{ChecksumFooter, PatchError, PatchReader};
+
+pub const MAGIC: &[u8] = b"BPS1";
+
+//Actions stored in the lowest 2 bits, the length minus one in the other bits
+const SOURCE_READ: usize = 0;
+const TARGET_READ: usize = 1;
+const SOURCE_COPY: usize = 2;
+const TARGET_COPY: usize = 3;
+
+/// BPS patches build the target from bytes of the rom at the same position, bytes of the
+/// patch and copies from anywhere in the rom or the already written target
+pub fn apply(rom: &[u8], patch: &[u8]) -> Result<Vec<u8>, PatchError> {
+    let footer = ChecksumFooter::verify(patch, rom)?;
+    let end = ChecksumFooter::start(patch);
+    let mut reader = PatchReader::new(&patch[..end], MAGIC.len());
+
+    let _source_size = reader.read_number()?;
+    let target_size = reader.read_target_size()?;
+    let metadata_size = reader.read_number()?;
+    reader.read_bytes(metadata_size)?;
+
+    let mut target: Vec<u8> = Vec::with_capacity(target_size);
+    let mut source_offset: usize = 0;
+    let mut target_offset: usize = 0;
+    while reader.position < end {
+        let action = reader.read_number()?;
+        let length = (action >> 2) + 1;
+        if target.len() + length > target_size {
+            return Err(PatchError::InvalidOffset(target.len() + length));
+        }
+
+        match action & 0x03 {
+            SOURCE_READ => {
+                let start = target.len();
+                target.extend_from_slice(read_source(rom, start, length)?);
+            }
+            TARGET_READ => target.extend_from_slice(reader.read_bytes(length)?),
+            SOURCE_COPY => {
+                source_offset = relative_offset(source_offset, reader.read_number()?)?;
+                target.extend_from_slice(read_source(rom, source_offset, length)?);
+                source_offset += length;
+            }
+            TARGET_COPY => {
+                target_offset = relative_offset(target_offset, reader.read_number()?)?;
+                //Byte by byte because the copy may overlap the bytes it writes
+                for _ in 0..length {
+                    let value = *target
+                        .get(target_offset)
+                        .ok_or(PatchError::InvalidOffset(target_offset))?;
+                    target.push(value);
+                    target_offset += 1;
+                }
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    footer.verify_target(&target)?;
+    Ok(target)
+}
+
+fn read_source(rom: &[u8], start: usize, length: usize) -> Result<&[u8], PatchError> {
+    rom.get(start..start + length)
+        .ok_or(PatchError::InvalidOffset(start))
+}
+
+/// The lowest bit is the sign of the distance to the previous offset
+fn relative_offset(offset: usize, value: usize) -> Result<usize, PatchError> {
+    let distance = value >> 1;
+    let result = if value & 0x01 == 0x01 {
+        offset.checked_sub(distance)
+    } else {
+        offset.checked_add(distance)
+    };
+
+    result.ok_or(PatchError::InvalidOffset(offset))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lib::patch::test_util::{append_footer, encode_number};
+
+    fn action(kind: usize, length: usize) -> Vec<u8> {
+        encode_number((length - 1) << 2 | kind)
+    }
+
+    fn create_patch(source: &[u8], target: &[u8], metadata: &[u8], actions: &[Vec<u8>]) -> Vec<u8> {
+        let mut patch = MAGIC.to_vec();
+        patch.extend(encode_number(source.len()));
+        patch.extend(encode_number(target.len()));
+        patch.extend(encode_number(metadata.len()));
+        patch.extend_from_slice(metadata);
+        for action in actions {
+            patch.extend_from_slice(action);
+        }
+        append_footer(&mut patch, source, target);
+        patch
+    }
+
+    #[test]
+    fn applies_all_actions() {
+        let source = [0, 1, 2, 3, 4, 5, 6, 7];
+        let target = [0, 1, 0xAA, 0xBB, 6, 7, 6, 7, 6, 7];
+        let actions = [
+            action(SOURCE_READ, 2),
+            [action(TARGET_READ, 2), vec![0xAA, 0xBB]].concat(),
+            //Forward by 6 from the start of the rom
+            [action(SOURCE_COPY, 2), encode_number(6 << 1)].concat(),
+            //From 6 at 4 of the target, overlapping the bytes written by the copy
+            [action(TARGET_COPY, 4), encode_number(4 << 1)].concat(),
+        ];
+        let patch = create_patch(&source, &target, b"<meta/>", &actions);
+        assert_eq!(apply(&source, &patch), Ok(target.to_vec()));
+    }
+
+    #[test]
+    fn copies_source_backwards() {
+        let source = [9, 8, 7, 6];
+        let target = [7, 6, 8, 7];
+        let actions = [
+            [action(SOURCE_COPY, 2), encode_number(2 << 1)].concat(),
+            //Source offset is 4 after the first copy
+            [action(SOURCE_COPY, 2), encode_number(3 << 1 | 1)].concat(),
+        ];
+        let patch = create_patch(&source, &target, &[], &actions);
+        assert_eq!(apply(&source, &patch), Ok(target.to_vec()));
+    }
+
+    #[test]
+    fn rejects_wrong_source() {
+        let patch = create_patch(&[1, 2], &[1, 2], &[], &[action(SOURCE_READ, 2)]);
+        assert!(matches!(
+            apply(&[1, 3], &patch),
+            Err(PatchError::SourceChecksumMismatch { .. })
+        ));
+    }
+
+    #[test]
+    fn rejects_copies_outside_rom() {
+        let actions = [[action(SOURCE_COPY, 2), encode_number(4 << 1)].concat()];
+        let patch = create_patch(&[1, 2], &[1, 2], &[], &actions);
+        assert_eq!(apply(&[1, 2], &patch), Err(PatchError::InvalidOffset(4)));
+    }
+
+    #[test]
+    fn rejects_oversized_target() {
+        let mut patch = MAGIC.to_vec();
+        patch.extend(encode_number(2));
+        patch.extend(encode_number(usize::MAX >> 8));
+        patch.extend(encode_number(0));
+        append_footer(&mut patch, &[1, 2], &[]);
+        assert_eq!(apply(&[1, 2], &patch), Err(PatchError::NumberTooLarge));
+    }
+
+    #[test]
+    fn rejects_damaged_patch() {
+        let mut patch = create_patch(&[1, 2], &[1, 2], &[], &[action(SOURCE_READ, 2)]);
+        patch[4] ^= 0x01;
+        assert!(matches!(
+            apply(&[1, 2], &patch),
+            Err(PatchError::PatchChecksumMismatch { .. })
+        ));
+    }
+}
@@ -0,0 +1,82 @@
+use crate::lib::patch::{PatchError, PatchReader};
+
+pub const MAGIC: &[u8] = b"PATCH";
+const END_OF_FILE: &[u8] = b"EOF";
+const OFFSET_SIZE: usize = 3;
+const LENGTH_SIZE: usize = 2;
+
+/// IPS patches replace bytes at 24 bit offsets. Records with length 0 repeat a single byte.
+/// IPS has no checksums, so any rom is accepted
+pub fn apply(rom: &[u8], patch: &[u8]) -> Result<Vec<u8>, PatchError> {
+    let mut target = rom.to_vec();
+    let mut reader = PatchReader::new(patch, MAGIC.len());
+
+    loop {
+        if patch[reader.position..].starts_with(END_OF_FILE) {
+            reader.position += END_OF_FILE.len();
+            break;
+        }
+
+        let offset = reader.read_big_endian(OFFSET_SIZE)?;
+        let length = reader.read_big_endian(LENGTH_SIZE)?;
+        if length == 0 {
+            let length = reader.read_big_endian(LENGTH_SIZE)?;
+            let value = reader.read_byte()?;
+            write(&mut target, offset, &vec![value; length]);
+        } else {
+            write(&mut target, offset, reader.read_bytes(length)?);
+        }
+    }
+
+    //Optional size the patched rom is truncated to
+    if let Ok(size) = reader.read_big_endian(OFFSET_SIZE) {
+        target.truncate(size);
+    }
+
+    Ok(target)
+}
+
+/// Writes behind the end of the rom extend it
+fn write(target: &mut Vec<u8>, offset: usize, data: &[u8]) {
+    let end = offset + data.len();
+    if target.len() < end {
+        target.resize(end, 0);
+    }
+    target[offset..end].copy_from_slice(data);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn patch(records: &[u8]) -> Vec<u8> {
+        [MAGIC, records, END_OF_FILE].concat()
+    }
+
+    #[test]
+    fn replaces_bytes() {
+        let patch = patch(&[0x00, 0x00, 0x01, 0x00, 0x02, 0xAA, 0xBB]);
+        assert_eq!(apply(&[0, 1, 2, 3], &patch), Ok(vec![0, 0xAA, 0xBB, 3]));
+    }
+
+    #[test]
+    fn repeats_run_length_encoded_bytes() {
+        let patch = patch(&[0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x03, 0x55]);
+        assert_eq!(apply(&[0; 5], &patch), Ok(vec![0, 0x55, 0x55, 0x55, 0]));
+    }
+
+    #[test]
+    fn extends_and_truncates_rom() {
+        let patch = patch(&[0x00, 0x00, 0x03, 0x00, 0x01, 0x77]);
+        assert_eq!(apply(&[1, 2], &patch), Ok(vec![1, 2, 0, 0x77]));
+
+        let truncating = [patch, vec![0x00, 0x00, 0x02]].concat();
+        assert_eq!(apply(&[1, 2, 3], &truncating), Ok(vec![1, 2]));
+    }
+
+    #[test]
+    fn rejects_truncated_patch() {
+        let patch = [MAGIC, &[0x00, 0x00, 0x01, 0x00, 0x04, 0xAA]].concat();
+        assert_eq!(apply(&[0; 8], &patch), Err(PatchError::UnexpectedEnd));
+    }
+}
@@ -0,0 +1,290 @@
+use crate::lib::cartridge::MAX_ROM_SIZE;
+use crate::lib::util::crc32::crc32;
+use std::fmt;
+
+pub mod bps;
+pub mod ips;
+pub mod ups;
+
+//Size of the source, target and patch CRC32 at the end of UPS and BPS patches
+const CHECKSUM_FOOTER_SIZE: usize = 12;
+
+/// Problems found while applying a patch
+#[derive(Clone, Debug, PartialEq)]
+pub enum PatchError {
+    UnknownFormat,
+    UnexpectedEnd,
+    InvalidOffset(usize),
+    NumberTooLarge,
+    PatchChecksumMismatch { expected: u32, calculated: u32 },
+    SourceChecksumMismatch { expected: u32, calculated: u32 },
+    TargetChecksumMismatch { expected: u32, calculated: u32 },
+}
+
+impl fmt::Display for PatchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PatchError::UnknownFormat => write!(f, "Patch is not an IPS, UPS or BPS patch"),
+            PatchError::UnexpectedEnd => write!(f, "Patch ends unexpectedly"),
+            PatchError::InvalidOffset(offset) => {
+                write!(f, "Patch accesses invalid offset 0x{:X}", offset)
+            }
+            PatchError::NumberTooLarge => write!(f, "Patch contains a number that is too large"),
+            PatchError::PatchChecksumMismatch {
+                expected,
+                calculated,
+            } => write!(
+                f,
+                "Patch is damaged: CRC32 is {:08X} but should be {:08X}",
+                calculated, expected
+            ),
+            PatchError::SourceChecksumMismatch {
+                expected,
+                calculated,
+            } => write!(
+                f,
+                "Patch is for a different ROM: CRC32 is {:08X} but should be {:08X}",
+                calculated, expected
+            ),
+            PatchError::TargetChecksumMismatch {
+                expected,
+                calculated,
+            } => write!(
+                f,
+                "Patched ROM is wrong: CRC32 is {:08X} but should be {:08X}",
+                calculated, expected
+            ),
+        }
+    }
+}
+
+impl std::error::Error for PatchError {}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PatchFormat {
+    Ips,
+    Ups,
+    Bps,
+}
+
+impl PatchFormat {
+    /// Detects the format by the magic number at the start of the patch
+    pub fn detect(patch: &[u8]) -> Option<Self> {
+        if patch.starts_with(ips::MAGIC) {
+            Some(PatchFormat::Ips)
+        } else if patch.starts_with(ups::MAGIC) {
+            Some(PatchFormat::Ups)
+        } else if patch.starts_with(bps::MAGIC) {
+            Some(PatchFormat::Bps)
+        } else {
+            None
+        }
+    }
+}
+
+/// Applies an IPS, UPS or BPS patch and returns the patched rom
+pub fn apply_patch(rom: &[u8], patch: &[u8]) -> Result<Vec<u8>, PatchError> {
+    match PatchFormat::detect(patch) {
+        Some(PatchFormat::Ips) => ips::apply(rom, patch),
+        Some(PatchFormat::Ups) => ups::apply(rom, patch),
+        Some(PatchFormat::Bps) => bps::apply(rom, patch),
+        None => Err(PatchError::UnknownFormat),
+    }
+}
+
+/// Reads patch data front to back
+pub struct PatchReader<'a> {
+    data: &'a [u8],
+    pub position: usize,
+}
+
+impl<'a> PatchReader<'a> {
+    pub fn new(data: &'a [u8], position: usize) -> Self {
+        PatchReader { data, position }
+    }
+
+    pub fn read_byte(&mut self) -> Result<u8, PatchError> {
+        let byte = *self
+            .data
+            .get(self.position)
+            .ok_or(PatchError::UnexpectedEnd)?;
+        self.position += 1;
+        Ok(byte)
+    }
+
+    pub fn read_bytes(&mut self, length: usize) -> Result<&'a [u8], PatchError> {
+        let end = self
+            .position
+            .checked_add(length)
+            .filter(|&end| end <= self.data.len())
+            .ok_or(PatchError::UnexpectedEnd)?;
+        let bytes = &self.data[self.position..end];
+        self.position = end;
+        Ok(bytes)
+    }
+
+    /// Big endian number of the given size like the offsets of IPS patches
+    pub fn read_big_endian(&mut self, size: usize) -> Result<usize, PatchError> {
+        let bytes = self.read_bytes(size)?;
+        Ok(bytes
+            .iter()
+            .fold(0, |value, &byte| value << 8 | byte as usize))
+    }
+
+    /// Variable length number of UPS and BPS patches. Every byte stores 7 bits and the
+    /// highest bit marks the last byte
+    pub fn read_number(&mut self) -> Result<usize, PatchError> {
+        let mut value: usize = 0;
+        let mut shift: usize = 1;
+        loop {
+            let byte = self.read_byte()?;
+            value = (byte as usize & 0x7F)
+                .checked_mul(shift)
+                .and_then(|bits| value.checked_add(bits))
+                .ok_or(PatchError::NumberTooLarge)?;
+            if byte & 0x80 != 0 {
+                return Ok(value);
+            }
+            shift = shift.checked_mul(0x80).ok_or(PatchError::NumberTooLarge)?;
+            value = value.checked_add(shift).ok_or(PatchError::NumberTooLarge)?;
+        }
+    }
+
+    /// Size of the patched rom. Checked before allocating it so broken patches can not request
+    /// more memory than any cartridge has
+    pub fn read_target_size(&mut self) -> Result<usize, PatchError> {
+        let size = self.read_number()?;
+        if size > MAX_ROM_SIZE {
+            return Err(PatchError::NumberTooLarge);
+        }
+
+        Ok(size)
+    }
+}
+
+/// Checksums at the end of UPS and BPS patches
+pub struct ChecksumFooter {
+    pub source: u32,
+    pub target: u32,
+}
+
+impl ChecksumFooter {
+    /// Checks the patch itself and the rom it is applied to
+    pub fn verify(patch: &[u8], rom: &[u8]) -> Result<Self, PatchError> {
+        if patch.len() < CHECKSUM_FOOTER_SIZE {
+            return Err(PatchError::UnexpectedEnd);
+        }
+
+        let footer = &patch[patch.len() - CHECKSUM_FOOTER_SIZE..];
+        let read_checksum =
+            |index: usize| u32::from_le_bytes(footer[index * 4..index * 4 + 4].try_into().unwrap());
+
+        let patch_checksum = read_checksum(2);
+        let calculated = crc32(&patch[..patch.len() - 4]);
+        if patch_checksum != calculated {
+            return Err(PatchError::PatchChecksumMismatch {
+                expected: patch_checksum,
+                calculated,
+            });
+        }
+
+        let footer = ChecksumFooter {
+            source: read_checksum(0),
+            target: read_checksum(1),
+        };
+        let calculated = crc32(rom);
+        if footer.source != calculated {
+            return Err(PatchError::SourceChecksumMismatch {
+                expected: footer.source,
+                calculated,
+            });
+        }
+
+        Ok(footer)
+    }
+
+    /// Start of the footer which ends the patch actions
+    pub fn start(patch: &[u8]) -> usize {
+        patch.len() - CHECKSUM_FOOTER_SIZE
+    }
+
+    pub fn verify_target(&self, target: &[u8]) -> Result<(), PatchError> {
+        let calculated = crc32(target);
+        if self.target != calculated {
+            return Err(PatchError::TargetChecksumMismatch {
+                expected: self.target,
+                calculated,
+            });
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+pub mod test_util {
+    use crate::lib::util::crc32::crc32;
+
+    /// Encodes a number like read_number decodes it
+    pub fn encode_number(mut value: usize) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        loop {
+            let bits = value & 0x7F;
+            value >>= 7;
+            if value == 0 {
+                bytes.push(0x80 | bits as u8);
+                return bytes;
+            }
+            bytes.push(bits as u8);
+            value -= 1;
+        }
+    }
+
+    /// Appends the source, target and patch CRC32
+    pub fn append_footer(patch: &mut Vec<u8>, source: &[u8], target: &[u8]) {
+        patch.extend_from_slice(&crc32(source).to_le_bytes());
+        patch.extend_from_slice(&crc32(target).to_le_bytes());
+        let patch_checksum = crc32(patch);
+        patch.extend_from_slice(&patch_checksum.to_le_bytes());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::test_util::encode_number;
+    use super::*;
+
+    #[test]
+    fn numbers_round_trip() {
+        for value in [0, 1, 0x7F, 0x80, 0x407F, 0x4080, 0x12345678] {
+            let encoded = encode_number(value);
+            assert_eq!(PatchReader::new(&encoded, 0).read_number(), Ok(value));
+        }
+    }
+
+    #[test]
+    fn rejects_target_size_above_largest_rom() {
+        let encoded = encode_number(MAX_ROM_SIZE);
+        assert_eq!(
+            PatchReader::new(&encoded, 0).read_target_size(),
+            Ok(MAX_ROM_SIZE)
+        );
+
+        let encoded = encode_number(MAX_ROM_SIZE + 1);
+        assert_eq!(
+            PatchReader::new(&encoded, 0).read_target_size(),
+            Err(PatchError::NumberTooLarge)
+        );
+    }
+
+    #[test]
+    fn detects_format_by_magic() {
+        assert_eq!(PatchFormat::detect(b"PATCHEOF"), Some(PatchFormat::Ips));
+        assert_eq!(PatchFormat::detect(b"UPS1"), Some(PatchFormat::Ups));
+        assert_eq!(PatchFormat::detect(b"BPS1"), Some(PatchFormat::Bps));
+        assert_eq!(
+            apply_patch(&[0; 4], b"NOPE"),
+            Err(PatchError::UnknownFormat)
+        );
+    }
+}
@@ -0,0 +1,118 @@
+use crate::lib::patch::{ChecksumFooter, PatchError, PatchReader};
+
+pub const MAGIC: &[u8] = b"UPS1";
+
+/// UPS patches XOR runs of bytes, each run starts a relative offset after the previous one
+/// and ends with a zero byte
+pub fn apply(rom: &[u8], patch: &[u8]) -> Result<Vec<u8>, PatchError> {
+    let footer = ChecksumFooter::verify(patch, rom)?;
+    let end = ChecksumFooter::start(patch);
+    let mut reader = PatchReader::new(&patch[..end], MAGIC.len());
+
+    let _source_size = reader.read_number()?;
+    let target_size = reader.read_target_size()?;
+    let mut target = rom.to_vec();
+    target.resize(target_size, 0);
+
+    let mut position: usize = 0;
+    while reader.position < end {
+        position = position
+            .checked_add(reader.read_number()?)
+            .ok_or(PatchError::NumberTooLarge)?;
+        loop {
+            let value = reader.read_byte()?;
+            if value == 0 {
+                break;
+            }
+            //Bytes behind the target size are ignored
+            if let Some(byte) = target.get_mut(position) {
+                *byte ^= value;
+            }
+            position += 1;
+        }
+        position += 1;
+    }
+
+    footer.verify_target(&target)?;
+    Ok(target)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lib::patch::test_util::{append_footer, encode_number};
+
+    fn create_patch(source: &[u8], target: &[u8], runs: &[(usize, &[u8])]) -> Vec<u8> {
+        let mut patch = MAGIC.to_vec();
+        patch.extend(encode_number(source.len()));
+        patch.extend(encode_number(target.len()));
+        for (skip, xor) in runs {
+            patch.extend(encode_number(*skip));
+            patch.extend_from_slice(xor);
+            patch.push(0);
+        }
+        append_footer(&mut patch, source, target);
+        patch
+    }
+
+    #[test]
+    fn xors_runs_at_relative_offsets() {
+        let source = [0x10, 0x20, 0x30, 0x40, 0x50, 0x60];
+        let target = [0x10, 0x21, 0x30, 0x40, 0x50, 0x6F];
+        //Second run starts 2 bytes after the zero byte ending the first run at 0x02
+        let patch = create_patch(&source, &target, &[(1, &[0x01]), (2, &[0x0F])]);
+        assert_eq!(apply(&source, &patch), Ok(target.to_vec()));
+    }
+
+    #[test]
+    fn resizes_to_target_size() {
+        let source = [1, 2, 3, 4];
+        let target = [1, 2, 3, 4, 0, 0xAB];
+        let patch = create_patch(&source, &target, &[(5, &[0xAB])]);
+        assert_eq!(apply(&source, &patch), Ok(target.to_vec()));
+
+        let shrinking = create_patch(&source, &[1, 2], &[]);
+        assert_eq!(apply(&source, &shrinking), Ok(vec![1, 2]));
+    }
+
+    #[test]
+    fn rejects_oversized_target() {
+        let mut patch = MAGIC.to_vec();
+        patch.extend(encode_number(4));
+        patch.extend(encode_number(0x1000000));
+        append_footer(&mut patch, &[1, 2, 3, 4], &[]);
+        assert_eq!(
+            apply(&[1, 2, 3, 4], &patch),
+            Err(PatchError::NumberTooLarge)
+        );
+    }
+
+    #[test]
+    fn rejects_wrong_source() {
+        let patch = create_patch(&[1, 2, 3], &[1, 2, 4], &[(2, &[0x07])]);
+        assert!(matches!(
+            apply(&[1, 2, 5], &patch),
+            Err(PatchError::SourceChecksumMismatch { .. })
+        ));
+    }
+
+    #[test]
+    fn rejects_damaged_patch() {
+        let mut patch = create_patch(&[1, 2, 3], &[1, 2, 4], &[(2, &[0x07])]);
+        patch[7] ^= 0xFF;
+        assert!(matches!(
+            apply(&[1, 2, 3], &patch),
+            Err(PatchError::PatchChecksumMismatch { .. })
+        ));
+    }
+
+    #[test]
+    fn rejects_wrong_target() {
+        //Footer claims a different result than the runs produce
+        let patch = create_patch(&[1, 2, 3], &[1, 2, 4], &[(2, &[0x01])]);
+        assert!(matches!(
+            apply(&[1, 2, 3], &patch),
+            Err(PatchError::TargetChecksumMismatch { .. })
+        ));
+    }
+}
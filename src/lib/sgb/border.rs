@@ -0,0 +1,122 @@
+use crate::lib::gpu::cgb_palette::convert_color;
+use crate::lib::gpu::{SGB_BUFFER_SIZE, SGB_SCREEN_HEIGHT, SGB_SCREEN_WIDTH};
+
+//SNES tiles use 4 bits per pixel
+const TILE_SIZE: usize = 32;
+const TILES: usize = 256;
+const TILES_SIZE: usize = TILES * TILE_SIZE;
+const MAP_WIDTH: usize = 32;
+const MAP_HEIGHT: usize = 28;
+const MAP_SIZE: usize = MAP_WIDTH * MAP_HEIGHT * 2;
+//The border uses SNES palettes 4-7 with 16 colors each
+const PALETTES_OFFSET: usize = 0x800;
+const FIRST_PALETTE: usize = 4;
+const PALETTES: usize = 4;
+const PALETTE_SIZE: usize = 16 * 2;
+const PALETTES_SIZE: usize = PALETTES * PALETTE_SIZE;
+
+/// Border around the game screen. Tiles are sent with CHR_TRN, map and palettes with PCT_TRN.
+/// The rendered border is cached since it only changes on a transfer
+pub struct Border {
+    tiles: [u8; TILES_SIZE],
+    map: [u8; MAP_SIZE],
+    palettes: [u8; PALETTES_SIZE],
+    buffer: [u8; SGB_BUFFER_SIZE],
+    //Color 0 of the border is transparent
+    opaque: [bool; SGB_SCREEN_WIDTH * SGB_SCREEN_HEIGHT],
+}
+
+impl Default for Border {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Border {
+    pub fn new() -> Border {
+        Border {
+            tiles: [0; TILES_SIZE],
+            map: [0; MAP_SIZE],
+            palettes: [0; PALETTES_SIZE],
+            buffer: [0; SGB_BUFFER_SIZE],
+            opaque: [false; SGB_SCREEN_WIDTH * SGB_SCREEN_HEIGHT],
+        }
+    }
+
+    /// CHR_TRN sends either the lower or the upper 128 tiles
+    pub fn transfer_tiles(&mut self, data: &[u8], upper_tiles: bool) {
+        let offset = if upper_tiles { TILES_SIZE / 2 } else { 0 };
+        self.tiles[offset..offset + TILES_SIZE / 2].copy_from_slice(&data[..TILES_SIZE / 2]);
+        self.render();
+    }
+
+    pub fn transfer_map(&mut self, data: &[u8]) {
+        self.map.copy_from_slice(&data[..MAP_SIZE]);
+        self.palettes
+            .copy_from_slice(&data[PALETTES_OFFSET..PALETTES_OFFSET + PALETTES_SIZE]);
+        self.render();
+    }
+
+    /// Draws the opaque pixels of the border over the buffer
+    pub fn draw(&self, buffer: &mut [u8; SGB_BUFFER_SIZE]) {
+        for (pixel, opaque) in self.opaque.iter().enumerate() {
+            if *opaque {
+                buffer[pixel * 3..pixel * 3 + 3]
+                    .copy_from_slice(&self.buffer[pixel * 3..pixel * 3 + 3]);
+            }
+        }
+    }
+
+    fn render(&mut self) {
+        for map_y in 0..MAP_HEIGHT {
+            for map_x in 0..MAP_WIDTH {
+                let entry_offset = (map_y * MAP_WIDTH + map_x) * 2;
+                let tile = self.map[entry_offset] as usize;
+                //Bits 2-4 select the palette, bit 6 flips x and bit 7 flips y
+                let options = self.map[entry_offset + 1];
+                let palette = ((options >> 2) & 0x07) as usize;
+
+                for y in 0..8 {
+                    for x in 0..8 {
+                        let tile_x = if options & 0x40 == 0x40 { 7 - x } else { x };
+                        let tile_y = if options & 0x80 == 0x80 { 7 - y } else { y };
+                        let color_index = self.read_tile_pixel(tile, tile_x, tile_y);
+
+                        let pixel = (map_y * 8 + y) * SGB_SCREEN_WIDTH + map_x * 8 + x;
+                        let color = self.get_color(palette, color_index);
+                        self.opaque[pixel] = color_index != 0;
+                        self.buffer[pixel * 3..pixel * 3 + 3].copy_from_slice(&color);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Bitplanes 0 and 1 are stored in the first 16 bytes of a tile, bitplanes 2 and 3 in the last 16
+    fn read_tile_pixel(&self, tile: usize, x: usize, y: usize) -> u8 {
+        let offset = tile * TILE_SIZE + y * 2;
+        let bit = 7 - x;
+
+        [
+            self.tiles[offset],
+            self.tiles[offset + 1],
+            self.tiles[offset + 16],
+            self.tiles[offset + 17],
+        ]
+        .iter()
+        .enumerate()
+        .fold(0, |color, (plane, data)| {
+            color | ((data >> bit) & 1) << plane
+        })
+    }
+
+    fn get_color(&self, palette: usize, color_index: u8) -> [u8; 3] {
+        //Palettes 0-3 are used by the game screen and are not available to the border
+        if palette < FIRST_PALETTE {
+            return [0; 3];
+        }
+
+        let offset = (palette - FIRST_PALETTE) * PALETTE_SIZE + color_index as usize * 2;
+        convert_color(self.palettes[offset] as u16 | (self.palettes[offset + 1] as u16) << 8)
+    }
+}
@@ -0,0 +1,421 @@
+pub mod border;
+pub mod packet;
+
+use crate::lib::gpu::cgb_palette::convert_color;
+use crate::lib::gpu::{
+    BUFFER_SIZE, SCREEN_HEIGHT, SCREEN_WIDTH, SGB_BUFFER_SIZE, SGB_SCREEN_WIDTH,
+};
+use crate::lib::io::joypad::Joypad;
+
+use border::Border;
+use packet::PacketReceiver;
+
+//The game screen is split into 20x18 cells of 8x8 pixels which each use one of 4 palettes
+const CELLS_X: usize = SCREEN_WIDTH / 8;
+const CELLS_Y: usize = SCREEN_HEIGHT / 8;
+const CELLS: usize = CELLS_X * CELLS_Y;
+
+//Position of the game screen inside the border
+const SCREEN_OFFSET_X: usize = 48;
+const SCREEN_OFFSET_Y: usize = 40;
+
+//VRAM transfers copy 4KB from the screen contents
+const TRANSFER_SIZE: usize = 4096;
+//The data has to be on screen in the frame after the command has been sent
+const TRANSFER_DELAY_FRAMES: u8 = 2;
+
+const SYSTEM_PALETTES: usize = 512;
+const ATTRIBUTE_FILE_SIZE: usize = 90;
+const ATTRIBUTE_FILES: usize = 45;
+
+//Default palette 1-A used before the game sends its own palettes
+const DEFAULT_PALETTE: [[u8; 3]; 4] =
+    [[248, 232, 200], [216, 144, 72], [168, 40, 32], [48, 24, 80]];
+
+#[derive(Clone, Copy)]
+enum Transfer {
+    SystemPalettes,
+    Tiles { upper_tiles: bool },
+    Border,
+    AttributeFiles,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum MaskMode {
+    None,
+    //Keeps the last frame on screen
+    Freeze,
+    Black,
+    Color0,
+}
+
+/// Super Game Boy. Receives commands through JOYP, colors the game screen
+/// with 4 palettes selected per 8x8 cell and draws a border around it
+pub struct Sgb {
+    packet_receiver: PacketReceiver,
+    palettes: [[[u8; 3]; 4]; 4],
+    system_palettes: [u8; SYSTEM_PALETTES * 8],
+    attributes: [u8; CELLS],
+    attribute_files: [u8; ATTRIBUTE_FILE_SIZE * ATTRIBUTE_FILES],
+    border: Border,
+    mask_mode: MaskMode,
+    pending_transfer: Option<(Transfer, u8)>,
+    player_count: u8,
+    current_player: u8,
+    joypad_select: u8,
+    screen_buffer: [u8; SGB_BUFFER_SIZE],
+}
+
+impl Default for Sgb {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Sgb {
+    pub fn new() -> Sgb {
+        Sgb {
+            packet_receiver: PacketReceiver::new(),
+            palettes: [DEFAULT_PALETTE; 4],
+            system_palettes: [0; SYSTEM_PALETTES * 8],
+            attributes: [0; CELLS],
+            attribute_files: [0; ATTRIBUTE_FILE_SIZE * ATTRIBUTE_FILES],
+            border: Border::new(),
+            mask_mode: MaskMode::None,
+            pending_transfer: None,
+            player_count: 1,
+            current_player: 0,
+            joypad_select: 0x30,
+            screen_buffer: [0; SGB_BUFFER_SIZE],
+        }
+    }
+
+    pub fn write_joypad(&mut self, value: u8) {
+        //The next controller is selected on a rising edge of P15
+        if self.joypad_select & 0x20 == 0 && value & 0x20 == 0x20 {
+            self.current_player = (self.current_player + 1) % self.player_count;
+        }
+        self.joypad_select = value;
+
+        if let Some(data) = self.packet_receiver.write(value) {
+            self.execute_command(&data);
+        }
+    }
+
+    /// With multiplayer enabled JOYP reads 0xF minus the current controller if no group is selected.
+    /// Only the first controller is connected
+    pub fn read_joypad(&self, joypad: &Joypad) -> u8 {
+        let select = self.joypad_select & 0x30;
+
+        if self.player_count > 1 && select == 0x30 {
+            return 0xC0 | select | (0x0F - self.current_player);
+        }
+
+        if self.current_player != 0 {
+            return 0xC0 | select | 0x0F;
+        }
+
+        joypad.read_input(self.joypad_select)
+    }
+
+    fn execute_command(&mut self, data: &[u8]) {
+        match data[0] >> 3 {
+            0x00 => self.set_palette_pair(data, 0, 1),
+            0x01 => self.set_palette_pair(data, 2, 3),
+            0x02 => self.set_palette_pair(data, 0, 3),
+            0x03 => self.set_palette_pair(data, 1, 2),
+            0x04 => self.attribute_block(data),
+            0x05 => self.attribute_line(data),
+            0x06 => self.attribute_divide(data),
+            0x07 => self.attribute_characters(data),
+            0x0A => self.set_system_palettes(data),
+            0x0B => self.request_transfer(Transfer::SystemPalettes),
+            0x11 => self.request_multiplayer(data[1]),
+            0x13 => self.request_transfer(Transfer::Tiles {
+                upper_tiles: data[1] & 0x01 == 0x01,
+            }),
+            0x14 => self.request_transfer(Transfer::Border),
+            0x15 => self.request_transfer(Transfer::AttributeFiles),
+            0x16 => self.set_attribute_file(data[1]),
+            0x17 => self.mask_mode = mask_mode_from(data[1]),
+            //Sound, SNES program and system commands are not supported
+            _ => {}
+        }
+    }
+
+    /// PAL01-PAL12 set colors 1-3 of two palettes. Color 0 is shared by all palettes
+    fn set_palette_pair(&mut self, data: &[u8], first: usize, second: usize) {
+        self.set_color0(read_color(data, 1));
+
+        for color in 0..3 {
+            self.palettes[first][color + 1] = read_color(data, 3 + color * 2);
+            self.palettes[second][color + 1] = read_color(data, 9 + color * 2);
+        }
+    }
+
+    fn set_color0(&mut self, color: [u8; 3]) {
+        for palette in self.palettes.iter_mut() {
+            palette[0] = color;
+        }
+    }
+
+    /// ATTR_BLK colors the inside, the border and the outside of rectangles
+    fn attribute_block(&mut self, data: &[u8]) {
+        let data_sets = (data[1] as usize).min((data.len() - 2) / 6);
+
+        for data_set in data[2..2 + data_sets * 6].chunks(6) {
+            let control = data_set[0] & 0x07;
+            let palette_inside = data_set[1] & 0x03;
+            let palette_border = (data_set[1] >> 2) & 0x03;
+            let palette_outside = (data_set[1] >> 4) & 0x03;
+
+            //If only the inside or only the outside is changed the border uses that palette as well
+            let (change_border, palette_border) = match control {
+                0x01 => (true, palette_inside),
+                0x04 => (true, palette_outside),
+                _ => (control & 0x02 == 0x02, palette_border),
+            };
+
+            let (x1, y1) = (data_set[2] as usize, data_set[3] as usize);
+            let (x2, y2) = (data_set[4] as usize, data_set[5] as usize);
+
+            for y in 0..CELLS_Y {
+                for x in 0..CELLS_X {
+                    let inside_rectangle = x >= x1 && x <= x2 && y >= y1 && y <= y2;
+                    let on_border = inside_rectangle && (x == x1 || x == x2 || y == y1 || y == y2);
+
+                    let palette = if on_border {
+                        change_border.then_some(palette_border)
+                    } else if inside_rectangle {
+                        (control & 0x01 == 0x01).then_some(palette_inside)
+                    } else {
+                        (control & 0x04 == 0x04).then_some(palette_outside)
+                    };
+
+                    if let Some(palette) = palette {
+                        self.attributes[y * CELLS_X + x] = palette;
+                    }
+                }
+            }
+        }
+    }
+
+    /// ATTR_LIN colors whole rows or columns
+    fn attribute_line(&mut self, data: &[u8]) {
+        let data_sets = (data[1] as usize).min(data.len() - 2);
+
+        for &line in &data[2..2 + data_sets] {
+            let index = (line & 0x1F) as usize;
+            let palette = (line >> 5) & 0x03;
+
+            //Bit 7 set = horizontal line
+            if line & 0x80 == 0x80 {
+                if index < CELLS_Y {
+                    self.attributes[index * CELLS_X..(index + 1) * CELLS_X].fill(palette);
+                }
+            } else if index < CELLS_X {
+                for y in 0..CELLS_Y {
+                    self.attributes[y * CELLS_X + index] = palette;
+                }
+            }
+        }
+    }
+
+    /// ATTR_DIV splits the screen into two halves and the dividing line
+    fn attribute_divide(&mut self, data: &[u8]) {
+        let palette_after = data[1] & 0x03;
+        let palette_before = (data[1] >> 2) & 0x03;
+        let palette_line = (data[1] >> 4) & 0x03;
+        let horizontal = data[1] & 0x40 == 0x40;
+        let line = data[2] as usize;
+
+        for y in 0..CELLS_Y {
+            for x in 0..CELLS_X {
+                let position = if horizontal { y } else { x };
+
+                self.attributes[y * CELLS_X + x] = match position.cmp(&line) {
+                    std::cmp::Ordering::Less => palette_before,
+                    std::cmp::Ordering::Equal => palette_line,
+                    std::cmp::Ordering::Greater => palette_after,
+                };
+            }
+        }
+    }
+
+    /// ATTR_CHR sets the palette of single cells starting at a position, 4 cells per byte
+    fn attribute_characters(&mut self, data: &[u8]) {
+        let mut x = (data[1] as usize).min(CELLS_X - 1);
+        let mut y = (data[2] as usize).min(CELLS_Y - 1);
+        let data_sets = (data[3] as usize | (data[4] as usize) << 8).min(CELLS);
+        let vertical = data[5] == 1;
+        let palettes = &data[6..];
+
+        for data_set in 0..data_sets.min(palettes.len() * 4) {
+            let shift = 6 - (data_set % 4) * 2;
+            self.attributes[y * CELLS_X + x] = (palettes[data_set / 4] >> shift) & 0x03;
+
+            if vertical {
+                y += 1;
+                if y == CELLS_Y {
+                    y = 0;
+                    x = (x + 1) % CELLS_X;
+                }
+            } else {
+                x += 1;
+                if x == CELLS_X {
+                    x = 0;
+                    y = (y + 1) % CELLS_Y;
+                }
+            }
+        }
+    }
+
+    /// PAL_SET copies 4 palettes from the palettes sent with PAL_TRN
+    fn set_system_palettes(&mut self, data: &[u8]) {
+        for palette in 0..4 {
+            let number = (data[1 + palette * 2] as usize | (data[2 + palette * 2] as usize) << 8)
+                % SYSTEM_PALETTES;
+
+            for color in 0..4 {
+                self.palettes[palette][color] =
+                    read_color(&self.system_palettes, number * 8 + color * 2);
+            }
+        }
+
+        //Color 0 of the first palette is used for all palettes
+        self.set_color0(self.palettes[0][0]);
+
+        if data[9] & 0x80 == 0x80 {
+            self.set_attribute_file(data[9]);
+        }
+
+        if data[9] & 0x40 == 0x40 {
+            self.mask_mode = MaskMode::None;
+        }
+    }
+
+    /// Bits 0-5 select the attribute file. Bit 6 cancels the mask
+    fn set_attribute_file(&mut self, value: u8) {
+        let file = (value & 0x3F) as usize;
+
+        if file < ATTRIBUTE_FILES {
+            let offset = file * ATTRIBUTE_FILE_SIZE;
+
+            for (cell, attribute) in self.attributes.iter_mut().enumerate() {
+                let shift = 6 - (cell % 4) * 2;
+                *attribute = (self.attribute_files[offset + cell / 4] >> shift) & 0x03;
+            }
+        }
+
+        if value & 0x40 == 0x40 {
+            self.mask_mode = MaskMode::None;
+        }
+    }
+
+    /// MLT_REQ enables 1, 2 or 4 controllers
+    fn request_multiplayer(&mut self, value: u8) {
+        self.player_count = match value & 0x03 {
+            0x01 => 2,
+            0x03 => 4,
+            _ => 1,
+        };
+        self.current_player = 0;
+    }
+
+    fn request_transfer(&mut self, transfer: Transfer) {
+        self.pending_transfer = Some((transfer, TRANSFER_DELAY_FRAMES));
+    }
+
+    /// Called at the end of every frame with the shades of the game screen.
+    /// Returns the screen with border as RGB
+    pub fn render(&mut self, shades: &[u8; BUFFER_SIZE / 3]) -> &[u8; SGB_BUFFER_SIZE] {
+        self.step_transfer(shades);
+
+        if self.mask_mode == MaskMode::Freeze {
+            return &self.screen_buffer;
+        }
+
+        //The SNES backdrop shows color 0 where the border is transparent
+        let backdrop = self.palettes[0][0];
+        for pixel in self.screen_buffer.chunks_mut(3) {
+            pixel.copy_from_slice(&backdrop);
+        }
+
+        for y in 0..SCREEN_HEIGHT {
+            for x in 0..SCREEN_WIDTH {
+                let color = match self.mask_mode {
+                    MaskMode::None | MaskMode::Freeze => {
+                        let palette = self.attributes[(y / 8) * CELLS_X + x / 8] as usize;
+                        self.palettes[palette][shades[y * SCREEN_WIDTH + x] as usize]
+                    }
+                    MaskMode::Black => [0; 3],
+                    MaskMode::Color0 => backdrop,
+                };
+
+                let offset = ((y + SCREEN_OFFSET_Y) * SGB_SCREEN_WIDTH + x + SCREEN_OFFSET_X) * 3;
+                self.screen_buffer[offset..offset + 3].copy_from_slice(&color);
+            }
+        }
+
+        self.border.draw(&mut self.screen_buffer);
+        &self.screen_buffer
+    }
+
+    fn step_transfer(&mut self, shades: &[u8; BUFFER_SIZE / 3]) {
+        let (transfer, frames) = match self.pending_transfer {
+            Some(pending_transfer) => pending_transfer,
+            None => return,
+        };
+
+        if frames > 1 {
+            self.pending_transfer = Some((transfer, frames - 1));
+            return;
+        }
+
+        self.pending_transfer = None;
+        let data = read_transfer_data(shades);
+
+        match transfer {
+            Transfer::SystemPalettes => self.system_palettes.copy_from_slice(&data),
+            Transfer::Tiles { upper_tiles } => self.border.transfer_tiles(&data, upper_tiles),
+            Transfer::Border => self.border.transfer_map(&data),
+            Transfer::AttributeFiles => {
+                let length = self.attribute_files.len();
+                self.attribute_files.copy_from_slice(&data[..length]);
+            }
+        }
+    }
+}
+
+/// The screen is read as 2 bit tiles from left to right, top to bottom
+fn read_transfer_data(shades: &[u8; BUFFER_SIZE / 3]) -> [u8; TRANSFER_SIZE] {
+    let mut data = [0; TRANSFER_SIZE];
+
+    for (tile, tile_data) in data.chunks_mut(16).enumerate() {
+        let tile_x = (tile % CELLS_X) * 8;
+        let tile_y = (tile / CELLS_X) * 8;
+
+        for y in 0..8 {
+            for x in 0..8 {
+                let shade = shades[(tile_y + y) * SCREEN_WIDTH + tile_x + x];
+                tile_data[y * 2] |= (shade & 0x01) << (7 - x);
+                tile_data[y * 2 + 1] |= (shade >> 1) << (7 - x);
+            }
+        }
+    }
+
+    data
+}
+
+fn read_color(data: &[u8], offset: usize) -> [u8; 3] {
+    convert_color(data[offset] as u16 | (data[offset + 1] as u16) << 8)
+}
+
+fn mask_mode_from(value: u8) -> MaskMode {
+    match value & 0x03 {
+        0x01 => MaskMode::Freeze,
+        0x02 => MaskMode::Black,
+        0x03 => MaskMode::Color0,
+        _ => MaskMode::None,
+    }
+}
@@ -0,0 +1,97 @@
+pub const PACKET_SIZE: usize = 16;
+const PACKET_BITS: u8 = PACKET_SIZE as u8 * 8;
+
+//Values of P14 and P15 written to JOYP
+const RESET_PULSE: u8 = 0x00;
+const ZERO_PULSE: u8 = 0x20;
+const ONE_PULSE: u8 = 0x10;
+const NO_PULSE: u8 = 0x30;
+
+/// Decodes command packets sent bit by bit through P14 and P15.
+/// A reset pulse starts a packet followed by 128 bits (LSB first) and a stop bit.
+/// Every pulse is followed by writing both lines high
+pub struct PacketReceiver {
+    data: Vec<u8>,
+    bits_received: u8,
+    packets_expected: usize,
+    receiving: bool,
+    previous_pulse: u8,
+}
+
+impl Default for PacketReceiver {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PacketReceiver {
+    pub fn new() -> PacketReceiver {
+        PacketReceiver {
+            data: Vec::new(),
+            bits_received: 0,
+            packets_expected: 0,
+            receiving: false,
+            previous_pulse: NO_PULSE,
+        }
+    }
+
+    /// Returns all packets of a command once the last one has been received
+    pub fn write(&mut self, value: u8) -> Option<Vec<u8>> {
+        let pulse = value & 0x30;
+        let previous_pulse = self.previous_pulse;
+        self.previous_pulse = pulse;
+
+        //Pulses only count when both lines were high before
+        if previous_pulse != NO_PULSE {
+            return None;
+        }
+
+        match pulse {
+            RESET_PULSE => {
+                self.receiving = true;
+                self.bits_received = 0;
+                None
+            }
+            ZERO_PULSE if self.receiving => self.receive_bit(0),
+            ONE_PULSE if self.receiving => self.receive_bit(1),
+            _ => None,
+        }
+    }
+
+    fn receive_bit(&mut self, bit: u8) -> Option<Vec<u8>> {
+        if self.bits_received == PACKET_BITS {
+            self.receiving = false;
+
+            //Transfer is aborted if the stop bit is not 0
+            if bit != 0 {
+                self.data.clear();
+                return None;
+            }
+
+            return self.finish_packet();
+        }
+
+        if self.bits_received.is_multiple_of(8) {
+            self.data.push(0);
+        }
+
+        let last_byte = self.data.last_mut().unwrap();
+        *last_byte |= bit << (self.bits_received % 8);
+        self.bits_received += 1;
+
+        None
+    }
+
+    fn finish_packet(&mut self) -> Option<Vec<u8>> {
+        //The lower 3 bits of the first byte hold the number of packets of the command
+        if self.data.len() == PACKET_SIZE {
+            self.packets_expected = (self.data[0] & 0x07).max(1) as usize;
+        }
+
+        if self.data.len() < self.packets_expected * PACKET_SIZE {
+            return None;
+        }
+
+        Some(std::mem::take(&mut self.data))
+    }
+}
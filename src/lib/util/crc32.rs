@@ -0,0 +1,14 @@
+//Reflected polynomial of CRC-32 as used by zip, gzip and ROM databases
+const POLYNOMIAL: u32 = 0xEDB88320;
+
+pub fn crc32(data: &[u8]) -> u32 {
+    !data.iter().fold(0xFFFFFFFF, |crc, &byte| {
+        (0..8).fold(crc ^ byte as u32, |crc, _| {
+            if crc & 0x01 == 0x01 {
+                crc >> 1 ^ POLYNOMIAL
+            } else {
+                crc >> 1
+            }
+        })
+    })
+}
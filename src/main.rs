@@ -12,6 +12,7 @@ mod config;
 mod controls;
 mod emulation;
 mod graphics;
+mod rom_file;
 mod savegame;
 
 pub enum EmulationSignal {
@@ -0,0 +1,134 @@
+use flate2::read::GzDecoder;
+use rust_boy::lib::cartridge::MAX_ROM_SIZE;
+use std::fs::{self, File};
+use std::io::{self, Read};
+use std::path::Path;
+use zip::ZipArchive;
+
+const ROM_EXTENSIONS: [&str; 2] = ["gb", "gbc"];
+
+/// ROM read from a plain file or from a zip or gzip archive
+pub struct RomFile {
+    pub data: Vec<u8>,
+    //ROMs from archives get the path of the inner ROM next to the archive, so savegames and
+    //patches are named after the game instead of the archive
+    pub path: String,
+}
+
+impl RomFile {
+    /// Zip archives load the given entry or the first ROM inside
+    pub fn read(path: &str, archive_entry: Option<&str>) -> Result<Self, String> {
+        match extension(path).as_deref() {
+            Some("zip") => read_zip(path, archive_entry),
+            Some("gz") => read_gzip(path),
+            _ => {
+                let data = fs::read(path).map_err(|_| format!("Could not open file {}", path))?;
+                Ok(RomFile {
+                    data,
+                    path: path.to_string(),
+                })
+            }
+        }
+    }
+}
+
+/// Names of the ROMs inside a zip archive, empty for other files
+pub fn archive_entries(path: &str) -> Result<Vec<String>, String> {
+    if extension(path).as_deref() != Some("zip") {
+        return Ok(Vec::new());
+    }
+
+    Ok(rom_entries(&mut open_zip(path)?))
+}
+
+/// ROM names in the order they are stored in the archive
+fn rom_entries(archive: &mut ZipArchive<File>) -> Vec<String> {
+    (0..archive.len())
+        .filter_map(|index| Some(archive.by_index_raw(index).ok()?.name().to_string()))
+        .filter(|name| is_rom(name))
+        .collect()
+}
+
+fn read_zip(path: &str, archive_entry: Option<&str>) -> Result<RomFile, String> {
+    let mut archive = open_zip(path)?;
+    let entry = match archive_entry {
+        Some(entry) => entry.to_string(),
+        None => rom_entries(&mut archive)
+            .into_iter()
+            .next()
+            .ok_or(format!("No Game Boy ROM in {}", path))?,
+    };
+
+    let data = archive
+        .by_name(&entry)
+        .map_err(io::Error::from)
+        .and_then(read_limited)
+        .map_err(|error| format!("Could not read {} from {}: {}", entry, path, error))?;
+
+    Ok(RomFile {
+        data,
+        path: inner_path(path, &entry),
+    })
+}
+
+fn open_zip(path: &str) -> Result<ZipArchive<File>, String> {
+    let file = File::open(path).map_err(|_| format!("Could not open file {}", path))?;
+    ZipArchive::new(file).map_err(|error| format!("Could not open archive {}: {}", path, error))
+}
+
+/// The name stored in the gzip header is used if present, otherwise the name without .gz
+fn read_gzip(path: &str) -> Result<RomFile, String> {
+    let file = File::open(path).map_err(|_| format!("Could not open file {}", path))?;
+    let mut decoder = GzDecoder::new(file);
+    let data = read_limited(&mut decoder)
+        .map_err(|error| format!("Could not decompress {}: {}", path, error))?;
+
+    let name = decoder
+        .header()
+        .and_then(|header| header.filename())
+        .map(|filename| String::from_utf8_lossy(filename).into_owned())
+        .unwrap_or_else(|| {
+            let stem = Path::new(path).file_stem().unwrap_or_default();
+            stem.to_string_lossy().into_owned()
+        });
+
+    Ok(RomFile {
+        data,
+        path: inner_path(path, &name),
+    })
+}
+
+/// Stops after MAX_ROM_SIZE so archives with huge contents can not use up the memory
+fn read_limited(reader: impl Read) -> io::Result<Vec<u8>> {
+    let mut data = Vec::new();
+    reader
+        .take(MAX_ROM_SIZE as u64 + 1)
+        .read_to_end(&mut data)?;
+    if data.len() > MAX_ROM_SIZE {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("ROM is larger than {} MiB", MAX_ROM_SIZE >> 20),
+        ));
+    }
+
+    Ok(data)
+}
+
+/// Entry name without its directories next to the archive
+fn inner_path(archive_path: &str, entry: &str) -> String {
+    let filename = Path::new(entry).file_name().unwrap_or_default();
+    Path::new(archive_path)
+        .with_file_name(filename)
+        .to_string_lossy()
+        .into_owned()
+}
+
+fn is_rom(name: &str) -> bool {
+    extension(name).is_some_and(|extension| ROM_EXTENSIONS.contains(&extension.as_str()))
+}
+
+fn extension(path: &str) -> Option<String> {
+    Path::new(path)
+        .extension()
+        .map(|extension| extension.to_string_lossy().to_lowercase())
+}
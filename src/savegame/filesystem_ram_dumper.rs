@@ -1,36 +1,110 @@
 use rust_boy::lib::cartridge::RamDumper;
-use std::fs;
+use std::cell::Cell;
+use std::ffi::OsString;
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
 
 pub struct FilesystemRamDumper {
-    filename: String,
+    path: PathBuf,
+    //Savegames were written next to the ROM before the save directory existed
+    legacy_path: Option<PathBuf>,
+    backups: usize,
+    //Backups are only rotated by the first write so autosaves keep older sessions
+    backups_rotated: Cell<bool>,
 }
 
 impl FilesystemRamDumper {
-    pub fn new(rom_filename: &String) -> Self {
-        let rom_name = if rom_filename.ends_with(".gb") || rom_filename.ends_with(".gbc") {
-            &rom_filename[..rom_filename.len() - 3]
-        } else {
-            rom_filename
-        };
-
-        let savegame_filename = format!("{}.sav", rom_name);
+    /// Patched games get their own savegame named after the patch file
+    pub fn new(
+        rom_filename: &str,
+        patch_filename: Option<&str>,
+        directory: &str,
+        backups: usize,
+    ) -> Self {
+        let rom_path = Path::new(rom_filename);
+        let mut savegame_filename = rom_path.file_stem().unwrap_or_default().to_os_string();
+        if let Some(patch_filename) = patch_filename {
+            savegame_filename.push(" (");
+            savegame_filename.push(Path::new(patch_filename).file_name().unwrap_or_default());
+            savegame_filename.push(")");
+        }
+        savegame_filename.push(".sav");
 
         FilesystemRamDumper {
-            filename: savegame_filename,
+            path: Path::new(directory).join(savegame_filename),
+            legacy_path: patch_filename
+                .is_none()
+                .then(|| PathBuf::from(legacy_filename(rom_filename))),
+            backups,
+            backups_rotated: Cell::new(false),
+        }
+    }
+
+    /// Moves every backup one slot back and copies the current savegame to .sav.bak
+    fn rotate_backups(&self) -> io::Result<()> {
+        if self.backups == 0 || !self.path.exists() {
+            return Ok(());
+        }
+
+        for index in (1..self.backups).rev() {
+            let previous = self.backup_path(index - 1);
+            if previous.exists() {
+                fs::rename(previous, self.backup_path(index))?;
+            }
+        }
+
+        fs::copy(&self.path, self.backup_path(0))?;
+        Ok(())
+    }
+
+    fn backup_path(&self, index: usize) -> PathBuf {
+        if index == 0 {
+            self.path_with_suffix(".bak")
+        } else {
+            self.path_with_suffix(&format!(".bak.{}", index))
         }
     }
+
+    fn path_with_suffix(&self, suffix: &str) -> PathBuf {
+        let mut path = OsString::from(self.path.as_os_str());
+        path.push(suffix);
+        PathBuf::from(path)
+    }
 }
 
 impl RamDumper for FilesystemRamDumper {
-    fn dump(&self, data: &Vec<u8>) {
-        fs::write(&self.filename, data).unwrap();
-    }
+    /// Writes to a temporary file first so a crash never leaves a half written savegame
+    fn dump(&self, data: &[u8]) -> io::Result<()> {
+        if let Some(directory) = self.path.parent() {
+            fs::create_dir_all(directory)?;
+        }
 
-    fn load(&self) -> Option<Vec<u8>> {
-        if let Ok(data) = fs::read(&self.filename) {
-            return Some(data);
+        if !self.backups_rotated.get() {
+            self.rotate_backups()?;
+            self.backups_rotated.set(true);
         }
 
-        None
+        let temp_path = self.path_with_suffix(".tmp");
+        let mut file = File::create(&temp_path)?;
+        file.write_all(data)?;
+        file.sync_all()?;
+        fs::rename(temp_path, &self.path)
     }
+
+    fn load(&self) -> Option<Vec<u8>> {
+        fs::read(&self.path)
+            .ok()
+            .or_else(|| fs::read(self.legacy_path.as_ref()?).ok())
+    }
+}
+
+fn legacy_filename(rom_filename: &str) -> String {
+    let rom_name = if rom_filename.ends_with(".gb") || rom_filename.ends_with(".gbc") {
+        &rom_filename[..rom_filename.len() - 3]
+    } else {
+        rom_filename
+    };
+
+    format!("{}.sav", rom_name)
 }
@@ -0,0 +1,172 @@
+use proptest::prelude::*;
+use rust_boy::lib::cartridge::detection::{detect_unlicensed_mapper, KnownRoms, UnlicensedMapper};
+use rust_boy::lib::cartridge::header::{header_checksum, HEADER_CHECKSUM_ADDRESS};
+use rust_boy::lib::cartridge::sachen::unscramble_address;
+use rust_boy::lib::cartridge::{
+    self, HeaderValidation, Peripherals, LOGO_ADDRESS, NINTENDO_LOGO, TITLE_ADDRESS,
+};
+use rust_boy::lib::util::crc32::crc32;
+
+//Every cartridge type with a mapper
+const CARTRIDGE_TYPES: [u8; 27] = [
+    0x00, 0x01, 0x02, 0x03, 0x05, 0x06, 0x08, 0x09, 0x0B, 0x0C, 0x0D, 0x0F, 0x10, 0x11, 0x12, 0x13,
+    0x19, 0x1A, 0x1B, 0x1C, 0x1D, 0x1E, 0x20, 0x22, 0xFC, 0xFE, 0xFF,
+];
+
+#[derive(Debug, Clone)]
+enum Access {
+    Write(u16, u8),
+    WriteRam(u16, u8),
+    Read(u16),
+    ReadRam(u16),
+    Tick(u32),
+}
+
+fn access() -> impl Strategy<Value = Access> {
+    prop_oneof![
+        (0x0000..0x8000u16, any::<u8>()).prop_map(|(address, value)| Access::Write(address, value)),
+        (0xA000..0xC000u16, any::<u8>())
+            .prop_map(|(address, value)| Access::WriteRam(address, value)),
+        (0x0000..0x8000u16).prop_map(Access::Read),
+        (0xA000..0xC000u16).prop_map(Access::ReadRam),
+        (0..100_000u32).prop_map(Access::Tick),
+    ]
+}
+
+/// Roms with a header claiming a different rom and RAM size than the file has
+fn header_rom() -> impl Strategy<Value = Vec<u8>> {
+    (
+        prop::sample::select(CARTRIDGE_TYPES.to_vec()),
+        prop::sample::select(vec![0x8000usize, 0x10000, 0x40000]),
+        0..=0x0Au8,
+        0..=0x06u8,
+    )
+        .prop_map(|(cartridge_type, size, rom_size, ram_size)| {
+            let mut rom = vec![0; size];
+            rom[0x147] = cartridge_type;
+            rom[0x148] = rom_size;
+            rom[0x149] = ram_size;
+            rom
+        })
+}
+
+/// Sachen roms only have the Nintendo logo at the scrambled addresses read by the boot rom
+fn sachen_rom() -> impl Strategy<Value = Vec<u8>> {
+    prop::sample::select(vec![0x8000usize, 0x10000, 0x40000]).prop_map(|size| {
+        let mut rom = vec![0; size];
+        for (index, &value) in NINTENDO_LOGO.iter().enumerate() {
+            let address = unscramble_address((LOGO_ADDRESS + index) as u16 | 0x80);
+            rom[address as usize] = value;
+        }
+        rom
+    })
+}
+
+/// Wisdom Tree roms claim to be rom only but are bigger than 32 KiB
+fn wisdom_tree_rom() -> impl Strategy<Value = Vec<u8>> {
+    prop::sample::select(vec![0x10000usize, 0x40000, 0x100000]).prop_map(|size| {
+        let mut rom = vec![0; size];
+        rom[TITLE_ADDRESS..TITLE_ADDRESS + 11].copy_from_slice(b"WISDOM TREE");
+        rom
+    })
+}
+
+/// M161 multicarts contain 8 games of 32 KiB with a logo each
+fn m161_rom() -> impl Strategy<Value = Vec<u8>> {
+    let mut rom = vec![0; 8 * 0x8000];
+    for game in 0..8 {
+        let address = game * 0x8000 + LOGO_ADDRESS;
+        rom[address..address + NINTENDO_LOGO.len()].copy_from_slice(&NINTENDO_LOGO);
+    }
+    Just(rom)
+}
+
+/// MMM01 multicarts with a valid menu header in the last 32 KiB
+fn mmm01_rom() -> impl Strategy<Value = Vec<u8>> {
+    (
+        prop::sample::select(vec![0x10000usize, 0x40000]),
+        0x0B..=0x0Du8,
+    )
+        .prop_map(|(size, cartridge_type)| {
+            let mut rom = vec![0; size];
+            let menu = &mut rom[size - 0x8000..];
+            menu[LOGO_ADDRESS..LOGO_ADDRESS + NINTENDO_LOGO.len()].copy_from_slice(&NINTENDO_LOGO);
+            menu[0x147] = cartridge_type;
+            menu[HEADER_CHECKSUM_ADDRESS] = header_checksum(menu);
+            rom
+        })
+}
+
+/// Unlicensed mappers are detected by the rom contents instead of the cartridge type
+fn rom() -> impl Strategy<Value = Vec<u8>> {
+    prop_oneof![
+        6 => header_rom(),
+        1 => sachen_rom(),
+        1 => wisdom_tree_rom(),
+        1 => m161_rom(),
+        1 => mmm01_rom(),
+    ]
+}
+
+#[test]
+fn mmm01_cartridge_type_in_game_data_is_no_menu() {
+    //MBC1 rom with the MMM01 cartridge type at the header position of the last 32 KiB
+    let mut rom = vec![0; 0x10000];
+    rom[0x147] = 0x01;
+    rom[0x8147] = 0x0B;
+    assert_eq!(detect_unlicensed_mapper(&rom, &KnownRoms::new()), None);
+
+    //A logo without matching header checksum is no menu either
+    rom[0x8000 + LOGO_ADDRESS..0x8000 + LOGO_ADDRESS + NINTENDO_LOGO.len()]
+        .copy_from_slice(&NINTENDO_LOGO);
+    rom[0x8000 + HEADER_CHECKSUM_ADDRESS] = header_checksum(&rom[0x8000..]).wrapping_add(1);
+    assert_eq!(detect_unlicensed_mapper(&rom, &KnownRoms::new()), None);
+
+    rom[0x8000 + HEADER_CHECKSUM_ADDRESS] = header_checksum(&rom[0x8000..]);
+    assert_eq!(
+        detect_unlicensed_mapper(&rom, &KnownRoms::new()),
+        Some(UnlicensedMapper::Mmm01)
+    );
+}
+
+#[test]
+fn known_roms_are_detected_before_the_heuristics() {
+    let mut rom = vec![0; 0x10000];
+    rom[0x147] = 0x0B;
+    let mut known_roms = KnownRoms::new();
+    assert_eq!(
+        detect_unlicensed_mapper(&rom, &known_roms),
+        Some(UnlicensedMapper::Mmm01)
+    );
+
+    known_roms.insert(crc32(&rom), UnlicensedMapper::WisdomTree);
+    assert_eq!(
+        detect_unlicensed_mapper(&rom, &known_roms),
+        Some(UnlicensedMapper::WisdomTree)
+    );
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(200))]
+
+    #[test]
+    fn random_accesses_never_panic(rom in rom(), accesses in prop::collection::vec(access(), 0..500)) {
+        let mut cartridge =
+            cartridge::new_cartridge(rom, Peripherals::new(), HeaderValidation::WarnOnly, &KnownRoms::new())
+                .unwrap();
+
+        for access in accesses {
+            match access {
+                Access::Write(address, value) => cartridge.write(address, value),
+                Access::WriteRam(address, value) => cartridge.write_ram(address, value),
+                Access::Read(address) => {
+                    cartridge.read(address);
+                }
+                Access::ReadRam(address) => {
+                    cartridge.read_ram(address);
+                }
+                Access::Tick(cycles) => cartridge.tick(cycles),
+            }
+        }
+    }
+}